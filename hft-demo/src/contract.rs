@@ -1,13 +1,34 @@
 use cosmwasm_std::{
-    coin, has_coins, to_binary, Addr, BankMsg, Coin, CosmosMsg, Deps, DepsMut, Env, MessageInfo,
-    QueryResponse, Response, Uint128,
+    coin, has_coins, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Order, QueryResponse, Response, StdError, StdResult, Storage, Uint128,
 };
 
-use provwasm_std::{withdraw_coins, ProvenanceMsg, ProvenanceQuerier};
+use provwasm_std::{transfer_marker_coins, withdraw_coins, ProvenanceMsg, ProvenanceQuerier};
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InitMsg, MigrateMsg, QueryMsg, TraderStateResponse};
-use crate::state::{config, config_read, trader_bucket, trader_bucket_read, State, TraderState};
+use crate::msg::{
+    AllTradersResponse, EffectivePriceResponse, ExecuteMsg, InitMsg, LoanCapUtilizationResponse,
+    MigrateMsg, OracleQuery, PriceResponse, QueryMsg, TraderLeverageResponse, TraderStateResponse,
+    TraderSummary, TraderSummaryResponse,
+};
+use crate::state::{
+    config, config_read, legacy_config_read, trader_bucket, trader_bucket_read, Feature,
+    MarkerPool, PriceSource, State, TraderState,
+};
+
+// The max number of decimal places supported for the security and stablecoin denoms.
+const MAX_EXPONENT: u32 = 18;
+
+// The seconds in a (non-leap) year, for accruing annual interest.
+const SECONDS_PER_YEAR: u128 = 365 * 24 * 60 * 60;
+
+// The default and max number of traders returned by a single page.
+const DEFAULT_PAGE_LIMIT: u32 = 30;
+const MAX_PAGE_LIMIT: u32 = 100;
+
+// The features that can be toggled. Loans lets buys borrow stablecoin from the loan pool.
+const LOANS_FEATURE: &str = "loans";
+const FEATURES: [&str; 1] = [LOANS_FEATURE];
 
 /// Initialize the smart contract config state.
 pub fn instantiate(
@@ -16,10 +37,52 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InitMsg,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Ensure denom exponents are within the supported range. Large amounts can still overflow price
+    // scaling, which returns an error.
+    let security_exponent = msg.security_exponent.unwrap_or(0);
+    let stablecoin_exponent = msg.stablecoin_exponent.unwrap_or(0);
+    if security_exponent > MAX_EXPONENT || stablecoin_exponent > MAX_EXPONENT {
+        let errm = format!("denom exponents must be <= {}", MAX_EXPONENT);
+        return Err(ContractError::Std(StdError::generic_err(errm)));
+    }
+    // Ensure there's at least one price source and that any oracle addresses are valid. An oracle
+    // address is the primary source, and only falls back to any listed price sources.
+    let price_sources = match msg.oracle_address {
+        Some(address) => std::iter::once(PriceSource::Oracle { address })
+            .chain(msg.price_sources.unwrap_or_default())
+            .collect(),
+        None => msg
+            .price_sources
+            .unwrap_or_else(|| vec![PriceSource::Stub {}]),
+    };
+    if price_sources.is_empty() {
+        let errm = "at least one price source is required";
+        return Err(ContractError::Std(StdError::generic_err(errm)));
+    }
+    for source in price_sources.iter() {
+        if let PriceSource::Oracle { address } = source {
+            deps.api.addr_validate(address)?;
+        }
+    }
+    // Ensure there's at least one security to trade.
+    if msg.securities.is_empty() {
+        let errm = "at least one security is required";
+        return Err(ContractError::Std(StdError::generic_err(errm)));
+    }
     config(deps.storage).save(&State {
         contract_admin: info.sender,
-        security: msg.security,
+        securities: msg.securities,
         stablecoin: msg.stablecoin,
+        security_exponent,
+        stablecoin_exponent,
+        buy_cooldown: msg.buy_cooldown.unwrap_or(0),
+        private_trader_state: msg.private_trader_state.unwrap_or(false),
+        partial_buy: msg.partial_buy.unwrap_or(false),
+        price_sources,
+        security_pools: vec![],
+        stablecoin_pool: None,
+        features: vec![],
+        interest_bps: msg.interest_bps.unwrap_or(0),
     })?;
     Ok(Response::default())
 }
@@ -33,8 +96,20 @@ pub fn execute(
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     match msg {
         ExecuteMsg::AddTrader { address } => try_add_trader(deps, info, address),
-        ExecuteMsg::BuyStock { amount } => try_buy_stock(deps, env, info, amount),
-        ExecuteMsg::SellStock { amount } => try_sell_stock(deps, info, amount),
+        ExecuteMsg::RemoveTrader { address } => try_remove_trader(deps, info, address),
+        ExecuteMsg::SetLoanCap { address, loan_cap } => {
+            try_set_loan_cap(deps, info, address, loan_cap)
+        }
+        ExecuteMsg::Liquidate { address } => try_liquidate(deps, env, info, address),
+        ExecuteMsg::BuyStock { security, amount } => {
+            try_buy_stock(deps, env, info, security, amount)
+        }
+        ExecuteMsg::SellStock { security, amount } => {
+            try_sell_stock(deps, env, info, security, amount)
+        }
+        ExecuteMsg::ReadTraderState { address } => try_read_trader_state(deps, info, address),
+        ExecuteMsg::RefreshPools {} => try_refresh_pools(deps, info),
+        ExecuteMsg::SetFeature { name, enabled } => try_set_feature(deps, info, name, enabled),
     }
 }
 
@@ -51,10 +126,10 @@ fn try_add_trader(
     }
 
     // Query trader's stablecoin balance, ensuring it is non-zero.
-    // let balance: Coin = deps.querier.query_balance(&address, &state.stablecoin)?;
-    // if balance.amount.is_zero() {
-    //     return Err(ContractError::InsufficientFunds {});
-    // }
+    let balance: Coin = deps.querier.query_balance(&address, &state.stablecoin)?;
+    if balance.amount.is_zero() {
+        return Err(ContractError::InsufficientFunds {});
+    }
 
     // Load trader config bucket
     let mut bucket = trader_bucket(deps.storage);
@@ -65,8 +140,13 @@ fn try_add_trader(
         bucket.save(
             &trader_key,
             &TraderState {
-                loan_cap: Uint128(10_000_000_000_u128),
+                loan_cap: balance.amount.multiply_ratio(9u128, 1u128), // 10x leverage
                 loans: Uint128::zero(),
+                last_buy_ts: 0,
+                address: address.clone(),
+                interest_bps: state.interest_bps,
+                last_accrued: 0,
+                bad_debt: Uint128::zero(),
             },
         )?;
     }
@@ -75,11 +155,127 @@ fn try_add_trader(
     Ok(Response::default())
 }
 
+// Offboard a trader, once all loans have been repaid.
+fn try_remove_trader(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Load contract state and validate the message sender is the contact admin.
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Ensure the trader doesn't owe the loan pool anything.
+    let trader_key = deps.api.addr_canonicalize(&address)?;
+    let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    if !trader_state.loans.is_zero() {
+        return Err(ContractError::OutstandingLoans {});
+    }
+
+    // Remove trader state
+    trader_bucket(deps.storage).remove(&trader_key);
+    Ok(Response::default())
+}
+
+// Tighten or loosen a trader's credit, as long as it still covers their outstanding loans.
+fn try_set_loan_cap(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+    loan_cap: Uint128,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Load contract state and validate the message sender is the contact admin.
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Update the trader's loan cap
+    let trader_key = deps.api.addr_canonicalize(&address)?;
+    trader_bucket(deps.storage).update(&trader_key, |opt| -> Result<_, ContractError> {
+        match opt {
+            Some(mut ts) => {
+                if loan_cap < ts.loans {
+                    return Err(ContractError::LoanCapBelowOutstanding {});
+                }
+                ts.loan_cap = loan_cap;
+                Ok(ts)
+            }
+            None => Err(ContractError::UnknownTrader {}),
+        }
+    })?;
+    Ok(Response::default())
+}
+
+// Force the sale of an underwater trader's stock into the security pools, repaying as much of
+// their loans as the stock is worth. Any shortfall is written off as bad debt.
+fn try_liquidate(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Load contract state and validate the message sender is the contact admin.
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Ensure the trader's stock is worth less than their loans, including interest.
+    let trader_key = deps.api.addr_canonicalize(&address)?;
+    let mut trader_state = accrue_interest(deps.storage, &trader_key, env.block.time.seconds())?;
+    let trader = load_trader_state_response(deps.as_ref(), &state, &address)?;
+    let proceeds = stock_value(deps.as_ref(), &trader.securities, &state)?;
+    if proceeds >= trader_state.loans {
+        return Err(ContractError::NotUnderwater {});
+    }
+
+    // Transfer the trader's stock to the security pools.
+    let mut res = Response::new();
+    let trader_address = deps.api.addr_validate(&address)?;
+    let mut stablecoin_pool = None;
+    for balance in trader.securities.iter().filter(|b| !b.amount.is_zero()) {
+        let (security_pool, pool) = load_pool_addresses(deps.branch(), &state, &balance.denom)?;
+        let stock_msg = transfer_marker_coins(
+            balance.amount.u128(),
+            &balance.denom,
+            security_pool,
+            trader_address.clone(),
+        )?;
+        res.add_message(stock_msg);
+        stablecoin_pool = Some(pool);
+    }
+
+    // Send the proceeds back to the loan pool
+    if let Some(stablecoin_pool) = stablecoin_pool {
+        let loan_msg: CosmosMsg<ProvenanceMsg> = CosmosMsg::Bank(BankMsg::Send {
+            amount: vec![coin(proceeds.u128(), &state.stablecoin)],
+            to_address: stablecoin_pool.to_string(),
+        });
+        res.add_message(loan_msg);
+    }
+
+    // Write off the loans the stock couldn't cover.
+    let shortfall = Uint128(trader_state.loans.u128() - proceeds.u128());
+    trader_state.bad_debt += shortfall;
+    trader_state.loans = Uint128::zero();
+    trader_bucket(deps.storage).save(&trader_key, &trader_state)?;
+
+    res.add_attribute("action", "hft.liquidate");
+    res.add_attribute("trader", address);
+    res.add_attribute("proceeds", proceeds);
+    res.add_attribute("bad_debt", shortfall);
+    Ok(res)
+}
+
 // Allow a trader to buy stock, with borrowing up to a pre-configured loan cap.
 fn try_buy_stock(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    security: String,
     amount: Uint128,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     // Error if buy amount is zero or too many funds sent
@@ -87,37 +283,86 @@ fn try_buy_stock(
         return Err(ContractError::InvalidBuy {});
     }
 
-    // Error if trader sent zero funds and has reached or exceeded the loan cap
-    let trader_key = deps.api.addr_canonicalize(&info.sender.to_string())?;
-    let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    // Error if trader sent zero funds and has no room left under the loan cap to borrow.
+    let now = env.block.time.seconds();
+    let trader_key = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let trader_state = accrue_interest(deps.storage, &trader_key, now)?;
 
     if info.funds.is_empty() && trader_state.loans >= trader_state.loan_cap {
-        return Err(ContractError::LoanCapExceeded {
-            amount,
-            loans: trader_state.loans,
-            loan_cap: trader_state.loan_cap,
-        });
+        return Err(ContractError::InsufficientFunds {});
     }
 
     // Load security and stablecoin marker denoms.
     let config_state = config_read(deps.storage).load()?;
-    let security: &str = &config_state.security;
+
+    // Error if the trader bought too recently.
+    if now
+        < trader_state
+            .last_buy_ts
+            .saturating_add(config_state.buy_cooldown)
+    {
+        return Err(ContractError::Cooldown {});
+    }
+    let security: &str = &security;
     let stablecoin: &str = &config_state.stablecoin;
 
-    // Validate that any funds sent have the correct denom
+    // Validate that the security is traded and any funds sent have the correct denom
+    if !config_state
+        .securities
+        .iter()
+        .any(|denom| denom == security)
+    {
+        return Err(ContractError::InvalidFundsDenom {});
+    }
     if info.funds.len() == 1 && info.funds[0].denom != stablecoin {
         return Err(ContractError::InvalidFundsDenom {});
     }
 
     // Determine cost of purchase
-    let price: Coin = stock_price(deps.as_ref(), amount.u128(), security, stablecoin);
+    let mut amount = amount;
+    let mut price: Coin = stock_price(deps.as_ref(), security, amount.u128(), &config_state)?;
+
+    // Traders can only borrow while loans are enabled.
+    let loans_enabled = config_state.feature_enabled(LOANS_FEATURE);
+
+    // In partial buy mode, reduce the purchase to the most shares the trader can afford without
+    // exceeding the loan cap. Any funds left over are refunded below.
+    if config_state.partial_buy && !has_coins(info.funds.as_slice(), &price) {
+        let sent_amount = if info.funds.len() == 1 {
+            info.funds[0].amount.u128()
+        } else {
+            0
+        };
+        let max_loan_amount = if loans_enabled {
+            // Accrued interest can push loans over the cap, leaving nothing to borrow.
+            trader_state
+                .loan_cap
+                .u128()
+                .saturating_sub(trader_state.loans.u128())
+        } else {
+            0
+        };
+        let funds = sent_amount + max_loan_amount;
+        let shares = max_shares(deps.as_ref(), security, funds, &config_state)?;
+        if shares > 0 && shares < amount.u128() {
+            amount = Uint128(shares);
+            price = stock_price(deps.as_ref(), security, shares, &config_state)?;
+        }
+    }
 
     // Create response type we can update on the fly
     let mut res = Response::new();
+    let mut loaned = Uint128::zero();
 
     // Trader didn't sent enough to cover the purchase. Determine loan amount and ensure loan cap
     // isn't exceeded.
     if !has_coins(info.funds.as_slice(), &price) {
+        if !loans_enabled {
+            return Err(ContractError::FeatureDisabled {
+                name: LOANS_FEATURE.into(),
+            });
+        }
+
         // Determine amount to loan
         let sent_amount = if info.funds.len() == 1 {
             info.funds[0].amount
@@ -126,11 +371,15 @@ fn try_buy_stock(
         };
         let loan_amount = price.amount.u128() - sent_amount.u128();
 
-        // Ensure trader is under loan cap after borrowing.
-        let max_loan_amount = trader_state.loan_cap.u128() - trader_state.loans.u128();
+        // Ensure trader is under loan cap after borrowing. Accrued interest can push loans over the
+        // cap, leaving nothing to borrow.
+        let max_loan_amount = trader_state
+            .loan_cap
+            .u128()
+            .saturating_sub(trader_state.loans.u128());
         if loan_amount > max_loan_amount {
             return Err(ContractError::LoanCapExceeded {
-                amount,
+                amount: Uint128(loan_amount),
                 loans: trader_state.loans,
                 loan_cap: trader_state.loan_cap,
             });
@@ -150,6 +399,7 @@ fn try_buy_stock(
                 None => Err(ContractError::UnknownTrader {}),
             }
         })?;
+        loaned = Uint128(loan_amount);
 
     // Issue a refund if the funds sent aren't exactly the amount necessary.
     } else if info.funds.len() == 1 && info.funds[0].amount > price.amount {
@@ -162,6 +412,24 @@ fn try_buy_stock(
         res.add_message(refund_msg);
     }
 
+    // Record the buy time for cooldown checks.
+    trader_bucket(deps.storage).update(&trader_key, |opt| -> Result<_, ContractError> {
+        match opt {
+            Some(mut ts) => {
+                ts.last_buy_ts = now;
+                Ok(ts)
+            }
+            None => Err(ContractError::UnknownTrader {}),
+        }
+    })?;
+
+    // Add buy event attributes to the response, so activity can be tracked off-chain.
+    res.add_attribute("action", "hft.buy");
+    res.add_attribute("trader", &info.sender);
+    res.add_attribute("shares", amount);
+    res.add_attribute("cost", price.amount);
+    res.add_attribute("loan_amount", loaned);
+
     // Withdraw stock to trader's account.
     let stock_msg = withdraw_coins(security, amount.u128(), security, info.sender)?;
     res.add_message(stock_msg);
@@ -169,18 +437,130 @@ fn try_buy_stock(
     Ok(res)
 }
 
-// Determine the purchase price for a number of shares.
-fn stock_price(_deps: Deps, shares: u128, _security: &str, stablecoin: &str) -> Coin {
-    // TODO: Here's where we'd query an oracle smart contract for price of security in stablecoin.
-    // For now, assume a one-to-one value
-    let price_per_share: u128 = 1;
-    coin(price_per_share * shares, stablecoin)
+// Add simple interest on a trader's loans for the seconds since interest last accrued, saving and
+// returning the updated trader state.
+fn accrue_interest(
+    storage: &mut dyn Storage,
+    trader_key: &[u8],
+    now: u64,
+) -> Result<TraderState, ContractError> {
+    let mut trader_state = trader_bucket_read(storage).load(trader_key)?;
+    trader_state.loans += accrued_interest(&trader_state, now);
+    trader_state.last_accrued = now;
+    trader_bucket(storage).save(trader_key, &trader_state)?;
+    Ok(trader_state)
+}
+
+// Determine the simple interest owed on a trader's loans since interest last accrued.
+fn accrued_interest(trader_state: &TraderState, now: u64) -> Uint128 {
+    // Traders that haven't been touched since onboarding have nothing to accrue yet.
+    if trader_state.last_accrued == 0 || now <= trader_state.last_accrued {
+        return Uint128::zero();
+    }
+    let elapsed = (now - trader_state.last_accrued) as u128;
+    trader_state.loans.multiply_ratio(
+        trader_state.interest_bps as u128 * elapsed,
+        10_000 * SECONDS_PER_YEAR,
+    )
+}
+
+// Determine the price of one share in stablecoin, using the first price source that returns a
+// valid (non-zero) price.
+fn share_price(deps: Deps, security: &str, state: &State) -> Result<u128, ContractError> {
+    for source in state.price_sources.iter() {
+        match source {
+            PriceSource::Oracle { address } => {
+                let query = OracleQuery::Price {
+                    security: security.into(),
+                };
+                let res: StdResult<PriceResponse> = deps.querier.query_wasm_smart(address, &query);
+                match res {
+                    Ok(res) if !res.price.is_zero() => return Ok(res.price.u128()),
+                    _ => continue, // Fall back to the next source
+                }
+            }
+            PriceSource::Stub {} => return Ok(1), // Assume a one-to-one value
+        }
+    }
+    Err(ContractError::PriceUnavailable {})
+}
+
+// Determine the purchase price for a number of shares, scaling from security to stablecoin
+// decimal places. Errors if the shares don't convert to a whole amount of stablecoin.
+fn stock_price(
+    deps: Deps,
+    security: &str,
+    shares: u128,
+    state: &State,
+) -> Result<Coin, ContractError> {
+    let price_per_share = share_price(deps, security, state)?;
+    let (scale, den) = exponent_scaling(state);
+    let num = Uint128(price_per_share)
+        .checked_mul(Uint128(shares))
+        .and_then(|num| num.checked_mul(Uint128(scale)))
+        .map_err(StdError::overflow)?
+        .u128();
+    if !num.is_multiple_of(den) {
+        return Err(ContractError::FractionalPrice {});
+    }
+    Ok(coin(num / den, &state.stablecoin))
+}
+
+// Determine the factors that convert security decimal places to stablecoin decimal places, as a
+// multiplier and divisor. Only the difference in exponents is applied, to keep amounts small.
+fn exponent_scaling(state: &State) -> (u128, u128) {
+    if state.stablecoin_exponent >= state.security_exponent {
+        (
+            10u128.pow(state.stablecoin_exponent - state.security_exponent),
+            1,
+        )
+    } else {
+        (
+            1,
+            10u128.pow(state.security_exponent - state.stablecoin_exponent),
+        )
+    }
+}
+
+// Determine the combined value of security balances in stablecoin.
+fn stock_value(deps: Deps, securities: &[Coin], state: &State) -> Result<Uint128, ContractError> {
+    let mut value = Uint128::zero();
+    for balance in securities
+        .iter()
+        .filter(|balance| !balance.amount.is_zero())
+    {
+        let price = stock_price(deps, &balance.denom, balance.amount.u128(), state)?;
+        value = value
+            .checked_add(price.amount)
+            .map_err(StdError::overflow)?;
+    }
+    Ok(value)
+}
+
+// Determine the most whole shares that can be bought with an amount of stablecoin.
+fn max_shares(
+    deps: Deps,
+    security: &str,
+    funds: u128,
+    state: &State,
+) -> Result<u128, ContractError> {
+    let price_per_share = share_price(deps, security, state)?;
+    let (scale, div) = exponent_scaling(state);
+    let num = Uint128(funds)
+        .checked_mul(Uint128(div))
+        .map_err(StdError::overflow)?;
+    let den = Uint128(price_per_share)
+        .checked_mul(Uint128(scale))
+        .map_err(StdError::overflow)?;
+    Ok(num.u128() / den.u128())
 }
 
 // Sell stock, paying off any loans first.
 fn try_sell_stock(
-    deps: DepsMut,
+    mut deps: DepsMut,
+    env: Env,
     info: MessageInfo,
+    security: String,
     amount: Uint128,
 ) -> Result<Response<ProvenanceMsg>, ContractError> {
     // Ensure proper funds are sent for sells
@@ -188,32 +568,40 @@ fn try_sell_stock(
         return Err(ContractError::InvalidSell {});
     }
 
-    // Load trader state
-    let trader_key = deps.api.addr_canonicalize(&info.sender.to_string())?;
-    let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    // Load trader state, adding any interest owed so it's paid off before proceeds are returned.
+    let trader_key = deps.api.addr_canonicalize(info.sender.as_str())?;
+    let trader_state = accrue_interest(deps.storage, &trader_key, env.block.time.seconds())?;
 
     // Load security and stablecoin marker denoms.
     let config_state = config_read(deps.storage).load()?;
-    let security: &str = &config_state.security;
-    let security_pool: Addr = get_marker_address(deps.as_ref(), security)?;
+    let security: &str = &security;
     let stablecoin: &str = &config_state.stablecoin;
-    let stablecoin_pool: Addr = get_marker_address(deps.as_ref(), stablecoin)?;
 
-    // Ensure the trader sent the correct amount of stock
+    // Ensure the security is traded and the trader sent the correct amount of it
+    if !config_state
+        .securities
+        .iter()
+        .any(|denom| denom == security)
+    {
+        return Err(ContractError::InvalidFundsDenom {});
+    }
     if info.funds[0].denom != security || amount != info.funds[0].amount {
         return Err(ContractError::InvalidSell {});
     }
 
+    let (security_pool, stablecoin_pool) =
+        load_pool_addresses(deps.branch(), &config_state, security)?;
+
     // Create response type we can update on the fly
     let mut res = Response::new();
 
     // If the trader has no loans, just transfer the stock to the security pool and send
     // escrowed funds to the sender.
-    let proceeds = stock_price(deps.as_ref(), amount.u128(), security, stablecoin);
+    let proceeds = stock_price(deps.as_ref(), security, amount.u128(), &config_state)?;
     if trader_state.loans.is_zero() {
         // Send stablecoin to trader
         let bank_msg: CosmosMsg<ProvenanceMsg> = CosmosMsg::Bank(BankMsg::Send {
-            amount: vec![proceeds],
+            amount: vec![proceeds.clone()],
             to_address: info.sender.to_string(),
         });
         res.add_message(bank_msg);
@@ -271,6 +659,13 @@ fn try_sell_stock(
         })?;
     }
 
+    // Add sell event attributes to the response, so activity can be tracked off-chain.
+    res.add_attribute("action", "hft.sell");
+    res.add_attribute("trader", &info.sender);
+    res.add_attribute("shares", amount);
+    res.add_attribute("proceeds", proceeds.amount);
+    res.add_attribute("loan_repaid", proceeds.amount.min(trader_state.loans));
+
     // Send security back to stock pool
     let stock_msg: CosmosMsg<ProvenanceMsg> = CosmosMsg::Bank(BankMsg::Send {
         amount: info.funds,
@@ -281,6 +676,112 @@ fn try_sell_stock(
     Ok(res)
 }
 
+// Return trader state in the response data. Queries have no sender, so this is how trader
+// state is read when it's private: only the admin or the trader themselves may read it.
+fn try_read_trader_state(
+    deps: DepsMut,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Ensure the sender is the admin or the trader.
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin && info.sender.as_str() != address {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Serialize trader state into the response data
+    let trader_state = load_trader_state_response(deps.as_ref(), &state, &address)?;
+    let mut res = Response::new();
+    res.set_data(to_binary(&trader_state)?);
+    Ok(res)
+}
+
+// Enable or disable a feature without redeploying the contract.
+fn try_set_feature(
+    deps: DepsMut,
+    info: MessageInfo,
+    name: String,
+    enabled: bool,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Load contract state and validate the message sender is the contact admin.
+    let mut state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !FEATURES.contains(&name.as_str()) {
+        let errm = format!("unknown feature: {}", name);
+        return Err(ContractError::Std(StdError::generic_err(errm)));
+    }
+    match state
+        .features
+        .iter_mut()
+        .find(|feature| feature.name == name)
+    {
+        Some(feature) => feature.enabled = enabled,
+        None => state.features.push(Feature { name, enabled }),
+    }
+    config(deps.storage).save(&state)?;
+    Ok(Response::default())
+}
+
+// Re-resolve the security and stablecoin pool addresses, eg after a marker migration.
+fn try_refresh_pools(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response<ProvenanceMsg>, ContractError> {
+    // Load contract state and validate the message sender is the contact admin.
+    let state = config_read(deps.storage).load()?;
+    if info.sender != state.contract_admin {
+        return Err(ContractError::Unauthorized {});
+    }
+    cache_pool_addresses(deps, &state.securities)?;
+    Ok(Response::default())
+}
+
+// Load the cached pool addresses for a security and the stablecoin, resolving them on first use.
+fn load_pool_addresses(
+    deps: DepsMut,
+    state: &State,
+    security: &str,
+) -> Result<(Addr, Addr), ContractError> {
+    let security_pool = state
+        .security_pools
+        .iter()
+        .find(|pool| pool.denom == security);
+    match (security_pool, &state.stablecoin_pool) {
+        (Some(security_pool), Some(stablecoin_pool)) => {
+            Ok((security_pool.address.clone(), stablecoin_pool.clone()))
+        }
+        _ => cache_pool_addresses(deps, &[security.to_string()])
+            .map(|(pools, stablecoin_pool)| (pools[0].address.clone(), stablecoin_pool)),
+    }
+}
+
+// Resolve the pool addresses for securities and the stablecoin and cache them in config state.
+fn cache_pool_addresses(
+    deps: DepsMut,
+    securities: &[String],
+) -> Result<(Vec<MarkerPool>, Addr), ContractError> {
+    let mut state = config_read(deps.storage).load()?;
+    let mut pools = vec![];
+    for denom in securities.iter() {
+        let address = get_marker_address(deps.as_ref(), denom)?;
+        let pool = MarkerPool {
+            denom: denom.clone(),
+            address,
+        };
+        state
+            .security_pools
+            .retain(|cached| cached.denom != pool.denom);
+        state.security_pools.push(pool.clone());
+        pools.push(pool);
+    }
+    let stablecoin_pool = get_marker_address(deps.as_ref(), &state.stablecoin)?;
+    state.stablecoin_pool = Some(stablecoin_pool.clone());
+    config(deps.storage).save(&state)?;
+    Ok((pools, stablecoin_pool))
+}
+
 // Get the address for a marker or return an error if the marker doesn't exist.
 fn get_marker_address(deps: Deps, denom: &str) -> Result<Addr, ContractError> {
     let querier = ProvenanceQuerier::new(&deps.querier);
@@ -289,51 +790,260 @@ fn get_marker_address(deps: Deps, denom: &str) -> Result<Addr, ContractError> {
 }
 
 /// Handle query requests for trader loans
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<QueryResponse, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse, ContractError> {
     match msg {
         QueryMsg::GetTraderState { address } => try_get_trader_state(deps, address),
+        QueryMsg::GetTraderLeverage { address } => try_get_trader_leverage(deps, address),
+        QueryMsg::GetLoanCapUtilization { address } => try_get_loan_cap_utilization(deps, address),
+        QueryMsg::GetTraderSummary { address } => try_get_trader_summary(deps, address),
+        QueryMsg::GetEffectivePrice {
+            address,
+            security,
+            amount,
+        } => try_get_effective_price(deps, env, address, security, amount),
+        QueryMsg::GetAllTraders { start_after, limit } => {
+            try_get_all_traders(deps, start_after, limit)
+        }
     }
 }
 
-// Query for trader loan cap and debt.
+// Query for trader loan cap and debt, unless trader state is private.
 fn try_get_trader_state(deps: Deps, address: String) -> Result<QueryResponse, ContractError> {
-    // Load state
+    // Load state, ensuring trader state is public.
+    let state = config_read(deps.storage).load()?;
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Serialize and return response
+    let bin = to_binary(&load_trader_state_response(deps, &state, &address)?)?;
+    Ok(bin)
+}
+
+// Query for trader loans divided by equity (stock value plus stablecoin minus loans), unless
+// trader state is private.
+fn try_get_trader_leverage(deps: Deps, address: String) -> Result<QueryResponse, ContractError> {
+    // Load state, ensuring trader state is public.
+    let state = config_read(deps.storage).load()?;
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Determine equity, using the current price for the trader's stock.
+    let trader = load_trader_state_response(deps, &state, &address)?;
+    let assets = stock_value(deps, &trader.securities, &state)? + trader.stablecoin;
+    // Leverage is undefined unless the trader has positive equity.
+    let leverage = if assets > trader.loans {
+        let equity = assets.u128() - trader.loans.u128();
+        Some(Decimal::from_ratio(trader.loans, equity))
+    } else {
+        None
+    };
+    // Serialize and return response
+    let bin = to_binary(&TraderLeverageResponse { leverage })?;
+    Ok(bin)
+}
+
+// Query for trader loans divided by loan cap, unless trader state is private.
+fn try_get_loan_cap_utilization(
+    deps: Deps,
+    address: String,
+) -> Result<QueryResponse, ContractError> {
+    // Load state, ensuring trader state is public.
+    let state = config_read(deps.storage).load()?;
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Load trader state
     let trader_key = deps.api.addr_canonicalize(&address)?;
     let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    // Utilization is undefined for traders without a loan cap.
+    let utilization = if trader_state.loan_cap.is_zero() {
+        None
+    } else {
+        Some(Decimal::from_ratio(
+            trader_state.loans,
+            trader_state.loan_cap,
+        ))
+    };
+    // Serialize and return response
+    let bin = to_binary(&LoanCapUtilizationResponse { utilization })?;
+    Ok(bin)
+}
+
+// Query for everything a dashboard shows for a trader in one call, unless trader state is private.
+fn try_get_trader_summary(deps: Deps, address: String) -> Result<QueryResponse, ContractError> {
+    // Load state, ensuring trader state is public.
     let state = config_read(deps.storage).load()?;
-    // Get the amount of stock for the trader.
-    let security = match deps.querier.query_balance(&address, &state.security) {
-        Ok(balance) => balance.amount,
-        Err(_) => Uint128::zero(),
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    // Load balances, loans and loan cap
+    let trader = load_trader_state_response(deps, &state, &address)?;
+    let available_borrow = if trader.loan_cap > trader.loans {
+        Uint128(trader.loan_cap.u128() - trader.loans.u128())
+    } else {
+        Uint128::zero()
+    };
+    // PnL can only be determined when a price source responds.
+    let (equity, deficit) = match stock_value(deps, &trader.securities, &state) {
+        Ok(stock_value) => {
+            let assets = stock_value.u128().saturating_add(trader.stablecoin.u128());
+            let loans = trader.loans.u128();
+            (
+                Some(Uint128(assets.saturating_sub(loans))),
+                Some(Uint128(loans.saturating_sub(assets))),
+            )
+        }
+        Err(_) => (None, None),
+    };
+    // Serialize and return response
+    let bin = to_binary(&TraderSummaryResponse {
+        stablecoin_denom: state.stablecoin,
+        securities: trader.securities,
+        stablecoin: trader.stablecoin,
+        loans: trader.loans,
+        loan_cap: trader.loan_cap,
+        available_borrow,
+        equity,
+        deficit,
+    })?;
+    Ok(bin)
+}
+
+// Query the cost of shares for a trader, adding a year of interest on the part of the cost that
+// would be financed at the trader's current loan utilization, unless trader state is private.
+fn try_get_effective_price(
+    deps: Deps,
+    env: Env,
+    address: String,
+    security: String,
+    amount: Uint128,
+) -> Result<QueryResponse, ContractError> {
+    // Load state, ensuring trader state is public and the security is traded.
+    let state = config_read(deps.storage).load()?;
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    if !state.securities.contains(&security) {
+        return Err(ContractError::InvalidFundsDenom {});
+    }
+    // Load trader state, including interest that hasn't been added to loans yet.
+    let trader_key = deps.api.addr_canonicalize(&address)?;
+    let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    let loans = trader_state.loans + accrued_interest(&trader_state, env.block.time.seconds());
+    // Pro-rate the interest by loan utilization; traders without a loan cap can't borrow.
+    let base_price = stock_price(deps, &security, amount.u128(), &state)?.amount;
+    let interest = if trader_state.loan_cap.is_zero() {
+        Uint128::zero()
+    } else {
+        base_price.multiply_ratio(
+            loans.u128() * trader_state.interest_bps as u128,
+            trader_state.loan_cap.u128() * 10_000,
+        )
+    };
+    // Serialize and return response
+    let bin = to_binary(&EffectivePriceResponse {
+        base_price,
+        interest,
+        effective_price: base_price + interest,
+    })?;
+    Ok(bin)
+}
+
+// Query a page of onboarded traders with their loans and loan caps, unless trader state is private.
+fn try_get_all_traders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<QueryResponse, ContractError> {
+    // Load state, ensuring trader state is public.
+    let state = config_read(deps.storage).load()?;
+    if state.private_trader_state {
+        return Err(ContractError::Unauthorized {});
+    }
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    // Range starts are inclusive, so skip past the given trader by appending a zero byte.
+    let start = match start_after {
+        Some(address) => {
+            let mut key = deps.api.addr_canonicalize(&address)?.to_vec();
+            key.push(0);
+            Some(key)
+        }
+        None => None,
     };
+    let traders = trader_bucket_read(deps.storage)
+        .range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (key, trader_state) = item?;
+            // Traders onboarded before addresses were stored only have the canonical key.
+            let address = if trader_state.address.is_empty() {
+                deps.api.addr_humanize(&key.into())?.to_string()
+            } else {
+                trader_state.address
+            };
+            Ok(TraderSummary {
+                address,
+                loans: trader_state.loans,
+                loan_cap: trader_state.loan_cap,
+            })
+        })
+        .collect::<StdResult<Vec<TraderSummary>>>()?;
+    // Serialize and return response
+    let bin = to_binary(&AllTradersResponse { traders })?;
+    Ok(bin)
+}
+
+// Load trader loan cap and debt, along with security and stablecoin balances.
+fn load_trader_state_response(
+    deps: Deps,
+    state: &State,
+    address: &str,
+) -> Result<TraderStateResponse, ContractError> {
+    // Load state
+    let trader_key = deps.api.addr_canonicalize(address)?;
+    let trader_state = trader_bucket_read(deps.storage).load(&trader_key)?;
+    // Get the amount of each stock for the trader.
+    let securities = state
+        .securities
+        .iter()
+        .map(|denom| match deps.querier.query_balance(address, denom) {
+            Ok(balance) => balance,
+            Err(_) => coin(0, denom),
+        })
+        .collect();
     // Get the amount of stablecoin for the trader.
-    let stablecoin = match deps.querier.query_balance(&address, &state.stablecoin) {
+    let stablecoin = match deps.querier.query_balance(address, &state.stablecoin) {
         Ok(balance) => balance.amount,
         Err(_) => Uint128::zero(),
     };
-    // Serialize and return response
-    let bin = to_binary(&TraderStateResponse {
-        security,
+    Ok(TraderStateResponse {
+        securities,
         stablecoin,
         loans: trader_state.loans,
         loan_cap: trader_state.loan_cap,
-    })?;
-    Ok(bin)
+    })
 }
 
 /// Called when migrating a contract instance to a new code ID.
-pub fn migrate(_deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
-    // For now, we do nothing
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Upgrade config persisted before multiple securities were supported.
+    if config_read(deps.storage).load().is_err() {
+        let state = legacy_config_read(deps.storage).load()?.upgrade();
+        config(deps.storage).save(&state)?;
+    }
     Ok(Response::default())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::from_binary;
-    use cosmwasm_std::testing::{mock_env, mock_info, MOCK_CONTRACT_ADDR};
-    use provwasm_mocks::{mock_dependencies, must_read_binary_file};
-    use provwasm_std::{Marker, MarkerMsgParams, ProvenanceMsgParams};
+    use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{
+        attr, from_binary, from_slice, Api, ContractResult, OwnedDeps, Querier, QuerierResult,
+        QueryRequest, SystemResult, WasmQuery,
+    };
+    use provwasm_mocks::{mock_dependencies, must_read_binary_file, ProvenanceMockQuerier};
+    use provwasm_std::{Marker, MarkerMsgParams, ProvenanceMsgParams, ProvenanceQuery};
 
     // A helper function that will extract marker message params from a custom cosmos message.
     fn unwrap_marker_params(msg: &CosmosMsg<ProvenanceMsg>) -> &MarkerMsgParams {
@@ -355,8 +1065,9 @@ mod tests {
 
         // Give the contract a name
         let msg = InitMsg {
-            security: "security".into(),
+            securities: vec!["security".into()],
             stablecoin: "stablecoin".into(),
+            ..Default::default()
         };
 
         // Ensure no messages were created.
@@ -365,7 +1076,7 @@ mod tests {
 
         // Read state
         let config_state = config_read(&deps.storage).load().unwrap();
-        assert_eq!(config_state.security, "security");
+        assert_eq!(config_state.securities, vec!["security".to_string()]);
         assert_eq!(config_state.stablecoin, "stablecoin");
     }
 
@@ -373,7 +1084,7 @@ mod tests {
     fn add_trader() {
         // Create mocks.
         let mut deps = mock_dependencies(&[]);
-        let stablecoins = coin(0, "stablecoin");
+        let stablecoins = coin(100, "stablecoin");
         deps.querier
             .base
             .update_balance("trader", vec![stablecoins]);
@@ -384,8 +1095,9 @@ mod tests {
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
-                security: "security".into(),
+                securities: vec!["security".into()],
                 stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
@@ -416,16 +1128,56 @@ mod tests {
         assert_eq!(
             rep,
             TraderStateResponse {
-                security: Uint128::zero(),
-                stablecoin: Uint128::zero(),
+                securities: vec![coin(0, "security")],
+                stablecoin: Uint128(100),
                 loans: Uint128::zero(),
-                loan_cap: Uint128(10_000_000_000_u128),
+                loan_cap: Uint128(900),
             }
         );
     }
 
     #[test]
-    fn buy_with_funds() {
+    fn add_trader_without_funds() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(0, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Attempt to onboard a trader without any stablecoin.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InsufficientFunds {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn remove_trader() {
         // Create mocks.
         let mut deps = mock_dependencies(&[]);
         let stablecoins = coin(100, "stablecoin");
@@ -439,8 +1191,9 @@ mod tests {
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
-                security: "security".into(),
+                securities: vec!["security".into()],
                 stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
@@ -456,37 +1209,30 @@ mod tests {
         )
         .unwrap(); // panics on error
 
-        // Buy some stocks without requiring loans.
-        let funds = coin(100, "stablecoin");
-        let res = execute(
+        // Offboard the trader.
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("trader", &[funds]),
-            ExecuteMsg::BuyStock {
-                amount: Uint128(100),
+            mock_info("admin", &[]),
+            ExecuteMsg::RemoveTrader {
+                address: "trader".into(),
             },
         )
-        .unwrap();
+        .unwrap(); // panics on error
 
-        // Ensure just one message was returned; a message to send stock to the trader.
-        assert_eq!(res.messages.len(), 1);
-        let security_amount = coin(100, "security"); // Note: assumes price is 1-to-1
-        match unwrap_marker_params(&res.messages[0]) {
-            MarkerMsgParams::WithdrawCoins {
-                marker_denom,
-                coin,
-                recipient,
-            } => {
-                assert_eq!(marker_denom, "security");
-                assert_eq!(coin, &security_amount);
-                assert_eq!(recipient, &Addr::unchecked("trader"));
-            }
-            _ => panic!("expected marker withdraw params"),
-        }
+        // Ensure trader state no longer loads.
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderState {
+                address: "trader".into(),
+            },
+        );
+        assert!(res.is_err());
     }
 
     #[test]
-    fn buy_with_loan() {
+    fn remove_trader_with_loans() {
         // Create mocks.
         let mut deps = mock_dependencies(&[]);
         let stablecoins = coin(100, "stablecoin");
@@ -500,8 +1246,9 @@ mod tests {
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
-                security: "security".into(),
+                securities: vec!["security".into()],
                 stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
@@ -517,71 +1264,44 @@ mod tests {
         )
         .unwrap(); // panics on error
 
-        // Buy 300 securities, but only send 100 stablecoin, requiring loans of 200 stablecoin.
-        let funds = coin(100, "stablecoin");
-        let res = execute(
+        // Buy some stocks with a loan.
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("trader", &[funds]),
+            mock_info("trader", &[]),
             ExecuteMsg::BuyStock {
-                amount: Uint128(300),
+                security: "security".into(),
+                amount: Uint128(100),
             },
         )
         .unwrap();
 
-        // Ensure two messages were returned; one to take out the loan, one to send stock.
-        assert_eq!(res.messages.len(), 2);
-
-        // Assert expected amounts
-        let expected_loan = coin(200, "stablecoin");
-        let expected_security = coin(300, "security");
-        res.messages
-            .into_iter()
-            .for_each(|msg| match unwrap_marker_params(&msg) {
-                MarkerMsgParams::WithdrawCoins {
-                    marker_denom,
-                    coin,
-                    recipient,
-                } => {
-                    if marker_denom == "security" {
-                        assert_eq!(coin, &expected_security);
-                        assert_eq!(recipient, &Addr::unchecked("trader"));
-                    } else {
-                        assert_eq!(marker_denom, "stablecoin");
-                        assert_eq!(coin, &expected_loan);
-                        assert_eq!(recipient, &Addr::unchecked(MOCK_CONTRACT_ADDR));
-                    }
-                }
-                _ => panic!("expected marker withdraw params"),
-            });
-
-        // Query trader state
-        let bin = query(
-            deps.as_ref(),
+        // Attempt to offboard the trader.
+        let err = execute(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::GetTraderState {
+            mock_info("admin", &[]),
+            ExecuteMsg::RemoveTrader {
                 address: "trader".into(),
             },
         )
-        .unwrap(); // panics on error
+        .unwrap_err();
 
-        // Ensure trader state has the expected amount of loans captured
-        let rep: TraderStateResponse = from_binary(&bin).unwrap();
-        assert_eq!(rep.loans, Uint128(200));
+        // Ensure we got the expected error
+        match err {
+            ContractError::OutstandingLoans {} => {}
+            _ => panic!("unexpected error type"),
+        }
     }
 
     #[test]
-    fn sell_with_proceeds() {
+    fn set_loan_cap() {
         // Create mocks.
         let mut deps = mock_dependencies(&[]);
-
-        // Add expected markers to the mock querier
-        let bin = must_read_binary_file("testdata/security.json");
-        let security_marker: Marker = from_binary(&bin).unwrap();
-        let bin = must_read_binary_file("testdata/stablecoin.json");
-        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        let stablecoins = coin(100, "stablecoin");
         deps.querier
-            .with_markers(vec![security_marker, stablecoin_marker]);
+            .base
+            .update_balance("trader", vec![stablecoins]);
 
         // Init so we have config state.
         instantiate(
@@ -589,8 +1309,9 @@ mod tests {
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
-                security: "security".into(),
+                securities: vec!["security".into()],
                 stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
@@ -606,43 +1327,66 @@ mod tests {
         )
         .unwrap(); // panics on error
 
-        // Sell 100 securities, with zero trader loans to pay off.
-        let funds = coin(100, "security");
-        let res = execute(
+        // Borrow the entire loan cap.
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("trader", &[funds]),
-            ExecuteMsg::SellStock {
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(900),
+            },
+        )
+        .unwrap();
+
+        // Raise the loan cap.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::SetLoanCap {
+                address: "trader".into(),
+                loan_cap: Uint128(1_000),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure the trader can borrow up to the new cap.
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loan_cap, Uint128(1_000));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
                 amount: Uint128(100),
             },
         )
         .unwrap();
 
-        // Ensure two messages were returned; one to send stock to the pool, one to send stablecoin
-        // to the trader.
-        assert_eq!(res.messages.len(), 2);
+        // Attempt to lower the loan cap below the outstanding loans.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::SetLoanCap {
+                address: "trader".into(),
+                loan_cap: Uint128(500),
+            },
+        )
+        .unwrap_err();
 
-        // Validate bank transfer addresses and amounts.
-        res.messages.into_iter().for_each(|msg| match msg {
-            CosmosMsg::Bank(BankMsg::Send {
-                amount, to_address, ..
-            }) => {
-                assert_eq!(amount.len(), 1);
-                if to_address == Addr::unchecked("trader") {
-                    let expected_proceeds = coin(100, "stablecoin");
-                    assert_eq!(amount[0], expected_proceeds);
-                } else {
-                    assert_eq!(to_address, "security");
-                    let expected_security = coin(100, "security");
-                    assert_eq!(amount[0], expected_security);
-                }
-            }
-            _ => panic!("unexpected message type"),
-        });
+        // Ensure we got the expected error
+        match err {
+            ContractError::LoanCapBelowOutstanding {} => {}
+            _ => panic!("unexpected error type"),
+        }
     }
 
     #[test]
-    fn sell_with_loans() {
+    fn buy_with_funds() {
         // Create mocks.
         let mut deps = mock_dependencies(&[]);
         let stablecoins = coin(100, "stablecoin");
@@ -650,22 +1394,15 @@ mod tests {
             .base
             .update_balance("trader", vec![stablecoins]);
 
-        // Add expected markers to the mock querier
-        let bin = must_read_binary_file("testdata/security.json");
-        let security_marker: Marker = from_binary(&bin).unwrap();
-        let bin = must_read_binary_file("testdata/stablecoin.json");
-        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
-        deps.querier
-            .with_markers(vec![security_marker, stablecoin_marker]);
-
         // Init so we have config state.
         instantiate(
             deps.as_mut(),
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
-                security: "security".into(),
+                securities: vec!["security".into()],
                 stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
@@ -681,68 +1418,200 @@ mod tests {
         )
         .unwrap(); // panics on error
 
-        // Buy 300 securities, requiring loans of 200 stablecoin.
+        // Buy some stocks without requiring loans.
         let funds = coin(100, "stablecoin");
-        execute(
+        let res = execute(
             deps.as_mut(),
             mock_env(),
             mock_info("trader", &[funds]),
             ExecuteMsg::BuyStock {
-                amount: Uint128(300),
+                security: "security".into(),
+                amount: Uint128(100),
             },
         )
         .unwrap();
 
-        // Query trader state
-        let bin = query(
-            deps.as_ref(),
+        // Ensure just one message was returned; a message to send stock to the trader.
+        assert_eq!(res.messages.len(), 1);
+        let security_amount = coin(100, "security"); // Note: assumes price is 1-to-1
+        match unwrap_marker_params(&res.messages[0]) {
+            MarkerMsgParams::WithdrawCoins {
+                marker_denom,
+                coin,
+                recipient,
+            } => {
+                assert_eq!(marker_denom, "security");
+                assert_eq!(coin, &security_amount);
+                assert_eq!(recipient, &Addr::unchecked("trader"));
+            }
+            _ => panic!("expected marker withdraw params"),
+        }
+    }
+
+    #[test]
+    fn buy_multiple_securities() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init with two securities.
+        instantiate(
+            deps.as_mut(),
             mock_env(),
-            QueryMsg::GetTraderState {
-                address: "trader".into(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into(), "bond".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap(); // panics on error
 
-        // Ensure trader state has the expected amount of loans captured
-        let rep: TraderStateResponse = from_binary(&bin).unwrap();
-        assert_eq!(rep.loans, Uint128(200));
-
-        // Sell all 300 securities
-        let funds = coin(300, "security");
-        let res = execute(
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("trader", &[funds]),
-            ExecuteMsg::SellStock {
-                amount: Uint128(300),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
             },
         )
-        .unwrap();
-
-        // Ensure three messages were returned; one to send stock to the security pool, one to send
-        // stablecoin to the loan pool (loan payment), and net proceeds to the trader.
-        assert_eq!(res.messages.len(), 3);
+        .unwrap(); // panics on error
 
-        // Validate bank transfer addresses and amounts.
-        res.messages.into_iter().for_each(|msg| match msg {
-            CosmosMsg::Bank(BankMsg::Send {
-                amount, to_address, ..
-            }) => {
-                assert_eq!(amount.len(), 1);
-                if to_address == Addr::unchecked("trader") {
-                    let expected_proceeds = coin(100, "stablecoin");
-                    assert_eq!(amount[0], expected_proceeds);
-                } else if to_address == Addr::unchecked("stablecoin") {
-                    let expected_loan_payment = coin(200, "stablecoin");
-                    assert_eq!(amount[0], expected_loan_payment);
-                } else {
-                    assert_eq!(to_address, "security");
-                    let expected_security = coin(300, "security");
-                    assert_eq!(amount[0], expected_security);
+        // Buy some of each security without requiring loans.
+        for (security, shares) in [("security", 100), ("bond", 50)] {
+            let funds = coin(shares, "stablecoin");
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("trader", &[funds]),
+                ExecuteMsg::BuyStock {
+                    security: security.into(),
+                    amount: Uint128(shares),
+                },
+            )
+            .unwrap();
+
+            // Ensure the stock was withdrawn from the marker for the security bought.
+            assert_eq!(res.messages.len(), 1);
+            match unwrap_marker_params(&res.messages[0]) {
+                MarkerMsgParams::WithdrawCoins {
+                    marker_denom,
+                    coin: withdrawn,
+                    recipient,
+                } => {
+                    assert_eq!(marker_denom, security);
+                    assert_eq!(withdrawn, &coin(shares, security));
+                    assert_eq!(recipient, &Addr::unchecked("trader"));
                 }
+                _ => panic!("expected marker withdraw params"),
             }
-            _ => panic!("unexpected message type"),
-        });
+        }
+
+        // Ensure securities that aren't configured can't be bought.
+        let funds = coin(10, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "unknown".into(),
+                amount: Uint128(10),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InvalidFundsDenom {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn buy_with_loan() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, but only send 100 stablecoin, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Ensure two messages were returned; one to take out the loan, one to send stock.
+        assert_eq!(res.messages.len(), 2);
+
+        // Ensure the buy event attributes were added.
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "hft.buy"),
+                attr("trader", "trader"),
+                attr("shares", "300"),
+                attr("cost", "300"),
+                attr("loan_amount", "200"),
+            ]
+        );
+
+        // Assert expected amounts
+        let expected_loan = coin(200, "stablecoin");
+        let expected_security = coin(300, "security");
+        res.messages
+            .into_iter()
+            .for_each(|msg| match unwrap_marker_params(&msg) {
+                MarkerMsgParams::WithdrawCoins {
+                    marker_denom,
+                    coin,
+                    recipient,
+                } => {
+                    if marker_denom == "security" {
+                        assert_eq!(coin, &expected_security);
+                        assert_eq!(recipient, &Addr::unchecked("trader"));
+                    } else {
+                        assert_eq!(marker_denom, "stablecoin");
+                        assert_eq!(coin, &expected_loan);
+                        assert_eq!(recipient, &Addr::unchecked(MOCK_CONTRACT_ADDR));
+                    }
+                }
+                _ => panic!("expected marker withdraw params"),
+            });
 
         // Query trader state
         let bin = query(
@@ -754,8 +1623,1990 @@ mod tests {
         )
         .unwrap(); // panics on error
 
-        // Ensure trader state has the loans paid off
+        // Ensure trader state has the expected amount of loans captured
         let rep: TraderStateResponse = from_binary(&bin).unwrap();
-        assert_eq!(rep.loans, Uint128::zero());
+        assert_eq!(rep.loans, Uint128(200));
+    }
+
+    #[test]
+    fn accrue_loan_interest() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init with a 10% annual interest rate.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                interest_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // A year later, buy 1 more security without borrowing.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(365 * 24 * 60 * 60);
+        let funds = coin(1, "stablecoin");
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(1),
+            },
+        )
+        .unwrap();
+
+        // Ensure a year of interest was added to the loans.
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loans, Uint128(220));
+    }
+
+    #[test]
+    fn buy_with_interest_over_cap() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init with a 10% annual interest rate.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                interest_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap of 900).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 1000 securities, borrowing up to the loan cap.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(1000),
+            },
+        )
+        .unwrap();
+
+        // A year later, interest has pushed loans over the cap. Buying with a loan must fail.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(365 * 24 * 60 * 60);
+        let funds = coin(1, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(2),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::LoanCapExceeded {
+                amount,
+                loans,
+                loan_cap,
+            } => {
+                assert_eq!(amount, Uint128(1));
+                assert_eq!(loans, Uint128(990));
+                assert_eq!(loan_cap, Uint128(900));
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn query_effective_price() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init with a 10% annual interest rate.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                interest_bps: Some(1_000),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Query the effective price of 90 more securities
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetEffectivePrice {
+                address: "trader".into(),
+                security: "security".into(),
+                amount: Uint128(90),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure a year of interest was added at the 200/900 loan utilization.
+        let rep: EffectivePriceResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            EffectivePriceResponse {
+                base_price: Uint128(90),
+                interest: Uint128(2),
+                effective_price: Uint128(92),
+            }
+        );
+    }
+
+    #[test]
+    fn buy_without_funds_at_cap() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Borrow the entire loan cap.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(9_000_000_000_u128),
+            },
+        )
+        .unwrap();
+
+        // Attempt another buy without sending any funds.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(1),
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InsufficientFunds {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn buy_with_partial_funds_over_cap() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Borrow all but 100 stablecoin of the loan cap.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(8_999_999_900_u128),
+            },
+        )
+        .unwrap();
+
+        // Buy 300 securities, sending 100 stablecoin, requiring a 200 stablecoin loan.
+        let funds = coin(100, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap_err();
+
+        // Ensure the error reports the loan required, not the shares requested.
+        match err {
+            ContractError::LoanCapExceeded {
+                amount,
+                loans,
+                loan_cap,
+            } => {
+                assert_eq!(amount, Uint128(200));
+                assert_eq!(loans, Uint128(8_999_999_900_u128));
+                assert_eq!(loan_cap, Uint128(9_000_000_000_u128));
+            }
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn sell_with_proceeds() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Add expected markers to the mock querier
+        let bin = must_read_binary_file("testdata/security.json");
+        let security_marker: Marker = from_binary(&bin).unwrap();
+        let bin = must_read_binary_file("testdata/stablecoin.json");
+        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        deps.querier
+            .with_markers(vec![security_marker, stablecoin_marker]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Sell 100 securities, with zero trader loans to pay off.
+        let funds = coin(100, "security");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+
+        // Ensure two messages were returned; one to send stock to the pool, one to send stablecoin
+        // to the trader.
+        assert_eq!(res.messages.len(), 2);
+
+        // Validate bank transfer addresses and amounts.
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(amount.len(), 1);
+                if to_address == Addr::unchecked("trader") {
+                    let expected_proceeds = coin(100, "stablecoin");
+                    assert_eq!(amount[0], expected_proceeds);
+                } else {
+                    assert_eq!(to_address, "security");
+                    let expected_security = coin(100, "security");
+                    assert_eq!(amount[0], expected_security);
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+    }
+
+    #[test]
+    fn sell_with_loans() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Add expected markers to the mock querier
+        let bin = must_read_binary_file("testdata/security.json");
+        let security_marker: Marker = from_binary(&bin).unwrap();
+        let bin = must_read_binary_file("testdata/stablecoin.json");
+        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        deps.querier
+            .with_markers(vec![security_marker, stablecoin_marker]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Query trader state
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderState {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure trader state has the expected amount of loans captured
+        let rep: TraderStateResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.loans, Uint128(200));
+
+        // Sell all 300 securities
+        let funds = coin(300, "security");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Ensure three messages were returned; one to send stock to the security pool, one to send
+        // stablecoin to the loan pool (loan payment), and net proceeds to the trader.
+        assert_eq!(res.messages.len(), 3);
+
+        // Ensure the sell event attributes were added.
+        assert_eq!(
+            res.attributes,
+            vec![
+                attr("action", "hft.sell"),
+                attr("trader", "trader"),
+                attr("shares", "300"),
+                attr("proceeds", "300"),
+                attr("loan_repaid", "200"),
+            ]
+        );
+
+        // Validate bank transfer addresses and amounts.
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(amount.len(), 1);
+                if to_address == Addr::unchecked("trader") {
+                    let expected_proceeds = coin(100, "stablecoin");
+                    assert_eq!(amount[0], expected_proceeds);
+                } else if to_address == Addr::unchecked("stablecoin") {
+                    let expected_loan_payment = coin(200, "stablecoin");
+                    assert_eq!(amount[0], expected_loan_payment);
+                } else {
+                    assert_eq!(to_address, "security");
+                    let expected_security = coin(300, "security");
+                    assert_eq!(amount[0], expected_security);
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Query trader state
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderState {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure trader state has the loans paid off
+        let rep: TraderStateResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.loans, Uint128::zero());
+    }
+
+    #[test]
+    fn buy_with_exponents() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with a stablecoin that has two more decimal places than the security.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                security_exponent: Some(0),
+                stablecoin_exponent: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 3 shares, sending 500 stablecoin when only 300 is required.
+        let funds = coin(500, "stablecoin");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(3),
+            },
+        )
+        .unwrap();
+
+        // Ensure two messages were returned; a refund of the overpayment and the stock withdraw.
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "trader");
+                assert_eq!(amount, &vec![coin(200, "stablecoin")]);
+            }
+            _ => panic!("expected refund bank send"),
+        }
+        match unwrap_marker_params(&res.messages[1]) {
+            MarkerMsgParams::WithdrawCoins {
+                coin: withdrawn, ..
+            } => {
+                assert_eq!(withdrawn, &coin(3, "security"))
+            }
+            _ => panic!("expected marker withdraw params"),
+        }
+    }
+
+    #[test]
+    fn buy_with_max_exponents() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with both denoms at the max decimal places.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                security_exponent: Some(18),
+                stablecoin_exponent: Some(18),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 1000 tokens of the security, sending 1000 tokens of stablecoin.
+        let tokens = 1_000 * 10u128.pow(18);
+        let funds = coin(tokens, "stablecoin");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(tokens),
+            },
+        )
+        .unwrap();
+        match unwrap_marker_params(&res.messages[0]) {
+            MarkerMsgParams::WithdrawCoins {
+                coin: withdrawn, ..
+            } => {
+                assert_eq!(withdrawn, &coin(tokens, "security"))
+            }
+            _ => panic!("expected marker withdraw params"),
+        }
+
+        // With a whole-share security, the same amount costs more stablecoin than can be
+        // represented. Ensure that returns an error instead of panicking.
+        config(&mut deps.storage)
+            .update(|mut state| -> StdResult<_> {
+                state.security_exponent = 0;
+                Ok(state)
+            })
+            .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(1, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(tokens),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Std(StdError::Overflow { .. }) => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn sell_with_exponents() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Add expected markers to the mock querier
+        let bin = must_read_binary_file("testdata/security.json");
+        let security_marker: Marker = from_binary(&bin).unwrap();
+        let bin = must_read_binary_file("testdata/stablecoin.json");
+        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        deps.querier
+            .with_markers(vec![security_marker, stablecoin_marker]);
+
+        // Init with a security that has three more decimal places than the stablecoin.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                security_exponent: Some(3),
+                stablecoin_exponent: Some(0),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Sell 2 whole shares (2000 base units), with zero trader loans to pay off.
+        let funds = coin(2000, "security");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(2000),
+            },
+        )
+        .unwrap();
+
+        // Ensure the trader gets 2 stablecoin for the sale.
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "trader");
+                assert_eq!(amount, &vec![coin(2, "stablecoin")]);
+            }
+            _ => panic!("expected proceeds bank send"),
+        }
+
+        // Selling a fraction of a share can't be converted to whole stablecoin.
+        let funds = coin(1500, "security");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(1500),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::FractionalPrice {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn buy_during_cooldown() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with a 10 second buy cooldown.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                buy_cooldown: Some(10),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // The first buy succeeds.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(100, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+
+        // A second buy a few seconds later is rejected.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(5);
+        let err = execute(
+            deps.as_mut(),
+            env,
+            mock_info("trader", &[coin(100, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Cooldown {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Once the cooldown has passed, buys are allowed again.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(10);
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("trader", &[coin(100, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn load_trader_state_before_cooldown() {
+        // Ensure trader entries persisted before buy cooldowns were added still load
+        let trader: TraderState = from_slice(br#"{"loan_cap":"100","loans":"10"}"#).unwrap();
+        assert_eq!(trader.loan_cap, Uint128(100));
+        assert_eq!(trader.loans, Uint128(10));
+        assert_eq!(trader.last_buy_ts, 0);
+    }
+
+    #[test]
+    fn read_private_trader_state() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init with private trader state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                private_trader_state: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure trader state can't be queried
+        let err = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderState {
+                address: "trader".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Ensure the trader and admin can read trader state
+        for sender in &["trader", "admin"] {
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(sender, &[]),
+                ExecuteMsg::ReadTraderState {
+                    address: "trader".into(),
+                },
+            )
+            .unwrap();
+            let rep: TraderStateResponse = from_binary(&res.data.unwrap()).unwrap();
+            assert_eq!(rep.stablecoin, Uint128(100));
+            assert_eq!(rep.loan_cap, Uint128(900));
+        }
+
+        // Ensure other accounts can't read trader state
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("other", &[]),
+            ExecuteMsg::ReadTraderState {
+                address: "trader".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn partial_buy_over_cap() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with partial buys enabled and a stablecoin that has two more decimal places.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                security_exponent: Some(0),
+                stablecoin_exponent: Some(2),
+                partial_buy: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Borrow the entire loan cap.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(90_000_000),
+            },
+        )
+        .unwrap();
+
+        // Buy 3 shares, sending 150 stablecoin when 300 is required.
+        let funds = coin(150, "stablecoin");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(3),
+            },
+        )
+        .unwrap();
+
+        // Ensure the buy was reduced to 1 share, refunding the remaining 50 stablecoin.
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "trader");
+                assert_eq!(amount, &vec![coin(50, "stablecoin")]);
+            }
+            _ => panic!("expected refund bank send"),
+        }
+        match unwrap_marker_params(&res.messages[1]) {
+            MarkerMsgParams::WithdrawCoins {
+                coin: withdrawn, ..
+            } => {
+                assert_eq!(withdrawn, &coin(1, "security"))
+            }
+            _ => panic!("expected marker withdraw params"),
+        }
+
+        // Ensure no additional loan was taken.
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loans, Uint128(9_000_000_000_u128));
+    }
+
+    // A mock querier that answers oracle price queries, delegating everything else.
+    struct OracleMockQuerier {
+        base: ProvenanceMockQuerier,
+        oracle: String,
+        price: Uint128,
+    }
+
+    impl Querier for OracleMockQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<ProvenanceQuery> = from_slice(bin_request).unwrap();
+            match &request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, .. })
+                    if contract_addr == &self.oracle =>
+                {
+                    let res = PriceResponse { price: self.price };
+                    SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    #[test]
+    fn liquidate_underwater_trader() {
+        // Create mocks, where the oracle prices the security at 3 stablecoin.
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: OracleMockQuerier {
+                base: mock_dependencies(&[]).querier,
+                oracle: "oracle".into(),
+                price: Uint128(3),
+            },
+        };
+
+        // Add expected markers to the mock querier
+        let bin = must_read_binary_file("testdata/security.json");
+        let security_marker: Marker = from_binary(&bin).unwrap();
+        let bin = must_read_binary_file("testdata/stablecoin.json");
+        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        deps.querier
+            .base
+            .with_markers(vec![security_marker, stablecoin_marker]);
+
+        // Init with an oracle address.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                oracle_address: Some("oracle".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .base
+            .update_balance("trader", vec![coin(100, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 100 securities entirely with loans of 300 stablecoin.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+        deps.querier
+            .base
+            .base
+            .update_balance("trader", vec![coin(100, "security")]);
+
+        // Ensure traders whose stock covers their loans can't be liquidated.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::Liquidate {
+                address: "trader".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NotUnderwater {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Drop the price to 2 stablecoin, so the stock is worth 200 against 300 in loans.
+        deps.querier.price = Uint128(2);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::Liquidate {
+                address: "trader".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure the stock was moved to the security pool and the proceeds to the loan pool.
+        assert_eq!(res.messages.len(), 2);
+        match unwrap_marker_params(&res.messages[0]) {
+            MarkerMsgParams::TransferMarkerCoins {
+                coin: transferred,
+                to,
+                from,
+            } => {
+                assert_eq!(transferred, &coin(100, "security"));
+                assert_eq!(to, &Addr::unchecked("security"));
+                assert_eq!(from, &Addr::unchecked("trader"));
+            }
+            _ => panic!("expected marker transfer params"),
+        }
+        match &res.messages[1] {
+            CosmosMsg::Bank(BankMsg::Send { amount, to_address }) => {
+                assert_eq!(amount, &vec![coin(200, "stablecoin")]);
+                assert_eq!(to_address, "stablecoin");
+            }
+            _ => panic!("unexpected message type"),
+        }
+
+        // Ensure the loans were cleared, with the shortfall recorded as bad debt.
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loans, Uint128::zero());
+        assert_eq!(trader_state.bad_debt, Uint128(100));
+    }
+
+    #[test]
+    fn buy_with_fallback_price_source() {
+        // Create mocks, where only the second oracle responds with a price.
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: OracleMockQuerier {
+                base: mock_dependencies(&[]).querier,
+                oracle: "oracle2".into(),
+                price: Uint128(3),
+            },
+        };
+
+        // Init with two oracles, falling back to the stub price.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                price_sources: Some(vec![
+                    PriceSource::Oracle {
+                        address: "oracle1".into(),
+                    },
+                    PriceSource::Oracle {
+                        address: "oracle2".into(),
+                    },
+                    PriceSource::Stub {},
+                ]),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 2 shares, sending 10 stablecoin.
+        let funds = coin(10, "stablecoin");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(2),
+            },
+        )
+        .unwrap();
+
+        // Ensure the second oracle price was used; 2 shares cost 6, refunding 4 stablecoin.
+        assert_eq!(res.messages.len(), 2);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "trader");
+                assert_eq!(amount, &vec![coin(4, "stablecoin")]);
+            }
+            _ => panic!("expected refund bank send"),
+        }
+    }
+
+    #[test]
+    fn buy_with_oracle_price() {
+        // Create mocks, where the oracle prices the security at 3 stablecoin.
+        let mut deps = OwnedDeps {
+            storage: MockStorage::default(),
+            api: MockApi::default(),
+            querier: OracleMockQuerier {
+                base: mock_dependencies(&[]).querier,
+                oracle: "oracle".into(),
+                price: Uint128(3),
+            },
+        };
+
+        // Init with an oracle address and no fallback price sources.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                oracle_address: Some("oracle".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 3 shares, sending exactly the 9 stablecoin they cost at the oracle price.
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(9, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(3),
+            },
+        )
+        .unwrap();
+
+        // Ensure the cost scaled with the price, so there's no refund or loan.
+        assert_eq!(res.messages.len(), 1);
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loans, Uint128::zero());
+
+        // Point a new contract at an oracle that doesn't respond.
+        let mut deps = mock_dependencies(&[]);
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                oracle_address: Some("oracle".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure buys fail rather than falling back to a stub price.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(9, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(3),
+            },
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::PriceUnavailable {}));
+    }
+
+    #[test]
+    fn query_trader_leverage() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Set the trader balance to the purchased securities, with all stablecoin spent.
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(300, "security")]);
+
+        // Query trader leverage
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderLeverage {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure leverage is 200 in loans over 100 in equity (300 stock value - 200 loans).
+        let rep: TraderLeverageResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.leverage, Some(Decimal::percent(200)));
+    }
+
+    #[test]
+    fn query_trader_summary() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        let stablecoins = coin(100, "stablecoin");
+        deps.querier
+            .base
+            .update_balance("trader", vec![stablecoins]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        let funds = coin(100, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Set the trader balance to the purchased securities, with all stablecoin spent.
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(300, "security")]);
+
+        // Query the trader summary
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderSummary {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure equity is 300 stock value - 200 loans, with 700 of the loan cap left to borrow.
+        let rep: TraderSummaryResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            TraderSummaryResponse {
+                stablecoin_denom: "stablecoin".into(),
+                securities: vec![coin(300, "security")],
+                stablecoin: Uint128::zero(),
+                loans: Uint128(200),
+                loan_cap: Uint128(900),
+                available_borrow: Uint128(700),
+                equity: Some(Uint128(100)),
+                deficit: Some(Uint128::zero()),
+            }
+        );
+
+        // Drop the trader balance to 100 securities, leaving loans uncovered.
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(100, "security")]);
+
+        // Ensure the summary reports a deficit of 200 loans - 100 stock value, with no equity.
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetTraderSummary {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+        let rep: TraderSummaryResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.equity, Some(Uint128::zero()));
+        assert_eq!(rep.deficit, Some(Uint128(100)));
+    }
+
+    #[test]
+    fn query_all_traders() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+        deps.querier
+            .base
+            .update_balance("trader1", vec![coin(100, "stablecoin")]);
+        deps.querier
+            .base
+            .update_balance("trader2", vec![coin(200, "stablecoin")]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Onboard both traders (sets trader state, including loan cap).
+        for address in ["trader1", "trader2"] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("admin", &[]),
+                ExecuteMsg::AddTrader {
+                    address: address.into(),
+                },
+            )
+            .unwrap(); // panics on error
+        }
+
+        // Query all traders
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAllTraders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Ensure both traders are listed
+        let rep: AllTradersResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.traders.len(), 2);
+        assert!(rep.traders.contains(&TraderSummary {
+            address: "trader1".into(),
+            loans: Uint128::zero(),
+            loan_cap: Uint128(900),
+        }));
+        assert!(rep.traders.contains(&TraderSummary {
+            address: "trader2".into(),
+            loans: Uint128::zero(),
+            loan_cap: Uint128(1_800),
+        }));
+
+        // Ensure paging past the first trader returns the other one
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAllTraders {
+                start_after: Some(rep.traders[0].address.clone()),
+                limit: None,
+            },
+        )
+        .unwrap(); // panics on error
+        let page: AllTradersResponse = from_binary(&bin).unwrap();
+        assert_eq!(page.traders, vec![rep.traders[1].clone()]);
+    }
+
+    #[test]
+    fn sell_with_cached_pools() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Add expected markers to the mock querier
+        let bin = must_read_binary_file("testdata/security.json");
+        let mut security_marker: Marker = from_binary(&bin).unwrap();
+        let bin = must_read_binary_file("testdata/stablecoin.json");
+        let stablecoin_marker: Marker = from_binary(&bin).unwrap();
+        deps.querier
+            .with_markers(vec![security_marker.clone(), stablecoin_marker.clone()]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Sell securities, which resolves and caches the pool addresses.
+        let funds = coin(100, "security");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            state.security_pools,
+            vec![MarkerPool {
+                denom: "security".into(),
+                address: Addr::unchecked("security"),
+            }]
+        );
+        assert_eq!(state.stablecoin_pool, Some(Addr::unchecked("stablecoin")));
+
+        // Simulate a marker migration that moves the security pool.
+        security_marker.address = Addr::unchecked("security2");
+        deps.querier
+            .with_markers(vec![security_marker, stablecoin_marker]);
+
+        // Sell securities again
+        let funds = coin(100, "security");
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[funds]),
+            ExecuteMsg::SellStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap();
+
+        // Ensure the stock was sent to the cached security pool address.
+        match res.messages.last().unwrap() {
+            CosmosMsg::Bank(BankMsg::Send { to_address, .. }) => {
+                assert_eq!(to_address, "security")
+            }
+            _ => panic!("unexpected message type"),
+        }
+
+        // Ensure only the admin can refresh pools
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            ExecuteMsg::RefreshPools {},
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Refresh pools and ensure the cached addresses were updated
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::RefreshPools {},
+        )
+        .unwrap();
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(
+            state.security_pools,
+            vec![MarkerPool {
+                denom: "security".into(),
+                address: Addr::unchecked("security2"),
+            }]
+        );
+        assert_eq!(state.stablecoin_pool, Some(Addr::unchecked("stablecoin")));
+    }
+
+    #[test]
+    fn query_loan_cap_utilization() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Buy 300 securities, requiring loans of 200 stablecoin.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(100, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(300),
+            },
+        )
+        .unwrap();
+
+        // Query loan cap utilization
+        let msg = QueryMsg::GetLoanCapUtilization {
+            address: "trader".into(),
+        };
+        let bin = query(deps.as_ref(), mock_env(), msg.clone()).unwrap();
+
+        // Ensure utilization is 200 in loans over the loan cap.
+        let rep: LoanCapUtilizationResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep.utilization,
+            Some(Decimal::from_ratio(200u128, 9_000_000_000u128))
+        );
+
+        // Ensure a zero loan cap has no utilization instead of failing.
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        trader_bucket(&mut deps.storage)
+            .update(&trader_key, |trader| -> StdResult<_> {
+                let mut trader = trader.unwrap();
+                trader.loan_cap = Uint128::zero();
+                Ok(trader)
+            })
+            .unwrap();
+        let bin = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let rep: LoanCapUtilizationResponse = from_binary(&bin).unwrap();
+        assert_eq!(rep.utilization, None);
+    }
+
+    #[test]
+    fn disable_loans_feature() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init so we have config state.
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                securities: vec!["security".into()],
+                stablecoin: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Give the trader a stablecoin balance (sets the loan cap).
+        deps.querier
+            .base
+            .update_balance("trader", vec![coin(1_000_000_000, "stablecoin")]);
+
+        // Onboard the trader (sets trader state, including loan cap).
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::AddTrader {
+                address: "trader".into(),
+            },
+        )
+        .unwrap(); // panics on error
+
+        // Only the admin can toggle features, and only known features.
+        let set_feature = |name: &str| ExecuteMsg::SetFeature {
+            name: name.into(),
+            enabled: false,
+        };
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[]),
+            set_feature("loans"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            set_feature("margin"),
+        )
+        .unwrap_err();
+
+        // Disable loans
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            set_feature("loans"),
+        )
+        .unwrap();
+
+        // Ensure buys that need a loan are rejected.
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(10, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(100),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::FeatureDisabled { name } => assert_eq!(name, "loans"),
+            _ => panic!("unexpected error type"),
+        }
+
+        // Ensure cash buys still work, without taking a loan.
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(10, "stablecoin")]),
+            ExecuteMsg::BuyStock {
+                security: "security".into(),
+                amount: Uint128(10),
+            },
+        )
+        .unwrap();
+        let trader_key = deps.api.addr_canonicalize("trader").unwrap();
+        let trader_state = trader_bucket_read(&deps.storage).load(&trader_key).unwrap();
+        assert_eq!(trader_state.loans, Uint128::zero());
+    }
+
+    #[test]
+    fn migrate_legacy_config() {
+        // Create mocks.
+        let mut deps = mock_dependencies(&[]);
+
+        // Seed config saved before multiple securities were supported.
+        let key = cosmwasm_storage::to_length_prefixed(crate::state::CONFIG_KEY);
+        deps.storage.set(
+            &key,
+            br#"{"contract_admin":"admin","security":"stock","stablecoin":"stablecoin"}"#,
+        );
+
+        // Migrate
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        // Ensure config was upgraded to the current schema with defaults for newer fields.
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.contract_admin, Addr::unchecked("admin"));
+        assert_eq!(state.securities, vec!["stock".to_string()]);
+        assert_eq!(state.stablecoin, "stablecoin");
+        assert_eq!(state.security_exponent, 0);
+        assert_eq!(state.stablecoin_exponent, 0);
+        assert_eq!(state.buy_cooldown, 0);
+        assert_eq!(state.price_sources, vec![PriceSource::Stub {}]);
+        assert!(state.security_pools.is_empty());
+        assert_eq!(state.stablecoin_pool, None);
+
+        // Seed config with a cached security pool, saved just before multiple securities.
+        deps.storage.set(
+            &key,
+            br#"{"contract_admin":"admin","security":"stock","stablecoin":"stablecoin",
+            "security_exponent":2,"stablecoin_exponent":6,"buy_cooldown":10,
+            "private_trader_state":true,"partial_buy":false,"price_sources":[{"stub":{}}],
+            "security_pool":"stock-pool","stablecoin_pool":"stablecoin-pool","interest_bps":500}"#,
+        );
+
+        // Migrate
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        // Ensure the cached pool is kept for the security, along with the other settings.
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.securities, vec!["stock".to_string()]);
+        assert_eq!(state.security_exponent, 2);
+        assert_eq!(state.stablecoin_exponent, 6);
+        assert_eq!(state.buy_cooldown, 10);
+        assert!(state.private_trader_state);
+        assert_eq!(
+            state.security_pools,
+            vec![MarkerPool {
+                denom: "stock".into(),
+                address: Addr::unchecked("stock-pool"),
+            }]
+        );
+        assert_eq!(
+            state.stablecoin_pool,
+            Some(Addr::unchecked("stablecoin-pool"))
+        );
+        assert_eq!(state.interest_bps, 500);
+
+        // Migrating again leaves the upgraded config as is.
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+        assert_eq!(config_read(&deps.storage).load().unwrap(), state);
     }
 }
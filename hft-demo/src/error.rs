@@ -23,4 +23,18 @@ pub enum ContractError {
     InvalidFundsDenom {},
     #[error("UnknownTrader")]
     UnknownTrader {},
+    #[error("FractionalPrice")]
+    FractionalPrice {},
+    #[error("Cooldown")]
+    Cooldown {},
+    #[error("PriceUnavailable")]
+    PriceUnavailable {},
+    #[error("LoanCapBelowOutstanding")]
+    LoanCapBelowOutstanding {},
+    #[error("NotUnderwater")]
+    NotUnderwater {},
+    #[error("OutstandingLoans")]
+    OutstandingLoans {},
+    #[error("FeatureDisabled: {name:?}")]
+    FeatureDisabled { name: String },
 }
@@ -1,37 +1,133 @@
-use cosmwasm_std::Uint128;
+use crate::state::PriceSource;
+use cosmwasm_std::{Coin, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct InitMsg {
-    pub security: String,   // The denom of the stock pool marker
-    pub stablecoin: String, // The denom of the loan pool marker
+    pub securities: Vec<String>, // The denoms of the stock pool markers
+    pub stablecoin: String,      // The denom of the loan pool marker
+    pub security_exponent: Option<u32>, // The decimal places of the security denom (default 0)
+    pub stablecoin_exponent: Option<u32>, // The decimal places of the stablecoin denom (default 0)
+    pub buy_cooldown: Option<u64>, // The min seconds between buys for a trader (default 0)
+    pub private_trader_state: Option<bool>, // Only the admin or trader may read trader state
+    pub partial_buy: Option<bool>, // Reduce buys that would exceed the loan cap (default false)
+    pub price_sources: Option<Vec<PriceSource>>, // Security price sources in order (default stub)
+    pub oracle_address: Option<String>, // An oracle queried for the price before any price sources
+    pub interest_bps: Option<u16>, // The annual interest rate on loans, in basis points (default 0)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     AddTrader { address: String }, // Sets loan cap based on stablecoin balance.
-    BuyStock { amount: Uint128 },  // The shares to buy
-    SellStock { amount: Uint128 }, // The shares to sell
+    RemoveTrader { address: String }, // Offboard a trader with no outstanding loans.
+    SetLoanCap { address: String, loan_cap: Uint128 }, // Adjust a trader's loan cap (admin only)
+    Liquidate { address: String }, // Sell an underwater trader's stock to repay loans (admin only)
+    BuyStock { security: String, amount: Uint128 }, // The shares of a security to buy
+    SellStock { security: String, amount: Uint128 }, // The shares of a security to sell
+    ReadTraderState { address: String }, // Trader state for the admin or trader, returned in data
+    RefreshPools {},               // Re-resolve the cached marker pool addresses
+    SetFeature { name: String, enabled: bool }, // Toggle a feature (admin only)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetTraderState { address: String },
+    GetTraderState {
+        address: String,
+    },
+    GetTraderLeverage {
+        address: String,
+    },
+    GetLoanCapUtilization {
+        address: String,
+    },
+    GetTraderSummary {
+        address: String,
+    },
+    GetEffectivePrice {
+        address: String,
+        security: String,
+        amount: Uint128, // The shares to price
+    },
+    GetAllTraders {
+        start_after: Option<String>, // Page by trader address (storage key order)
+        limit: Option<u32>,          // Max traders to return (default 30, max 100)
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct TraderStateResponse {
-    pub security: Uint128,
+    pub securities: Vec<Coin>, // The balance of each configured security
     pub stablecoin: Uint128,
     pub loans: Uint128,
     pub loan_cap: Uint128,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TraderLeverageResponse {
+    pub leverage: Option<Decimal>, // Loans divided by equity, or None if equity isn't positive
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LoanCapUtilizationResponse {
+    pub utilization: Option<Decimal>, // Loans divided by loan cap, or None if the cap is zero
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TraderSummaryResponse {
+    pub stablecoin_denom: String,
+    pub securities: Vec<Coin>, // The balance of each configured security
+    pub stablecoin: Uint128,
+    pub loans: Uint128,
+    pub loan_cap: Uint128,
+    pub available_borrow: Uint128, // Loan cap minus loans
+    pub equity: Option<Uint128>, // Stock value plus stablecoin over loans, or None without a price
+    pub deficit: Option<Uint128>, // Loans over stock value plus stablecoin, or None without a price
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TraderSummary {
+    pub address: String,
+    pub loans: Uint128,
+    pub loan_cap: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AllTradersResponse {
+    pub traders: Vec<TraderSummary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct EffectivePriceResponse {
+    pub base_price: Uint128, // The stablecoin cost of the shares at the current price
+    pub interest: Uint128,   // A year of interest on the part of the cost financed by loans
+    pub effective_price: Uint128, // The base price plus interest
+}
+
+/// The query the demo sends to oracle price sources.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQuery {
+    Price { security: String },
+}
+
+/// The oracle response, containing the price of one security in stablecoin.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PriceResponse {
+    pub price: Uint128,
+}
+
 /// Migrate the contract.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
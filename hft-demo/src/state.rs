@@ -14,28 +14,145 @@ pub static TRADER_KEY: &[u8] = b"trader";
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub contract_admin: Addr, // Ensures only sender from contract init can call handle.
-    pub security: String,     // The denom of the stock pool marker.
+    pub securities: Vec<String>, // The denoms of the stock pool markers.
     pub stablecoin: String,   // The denom of the loan pool marker.
+    #[serde(default)]
+    pub security_exponent: u32, // The decimal places of the security denom.
+    #[serde(default)]
+    pub stablecoin_exponent: u32, // The decimal places of the stablecoin denom.
+    #[serde(default)]
+    pub buy_cooldown: u64, // The min seconds required between buys for a trader.
+    pub private_trader_state: bool, // Disables trader state queries in favor of authorized reads.
+    pub partial_buy: bool,    // Buy the max shares under the loan cap instead of rejecting.
+    pub price_sources: Vec<PriceSource>, // Where to get the security price, tried in order.
+    pub security_pools: Vec<MarkerPool>, // The cached security marker addresses.
+    pub stablecoin_pool: Option<Addr>, // The cached stablecoin marker address.
+    #[serde(default)]
+    pub features: Vec<Feature>, // Feature toggles; features that aren't listed are enabled.
+    #[serde(default)]
+    pub interest_bps: u16, // The annual interest rate charged on loans to new traders.
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceSource {
+    Oracle { address: String }, // An oracle smart contract that responds to price queries.
+    Stub {},                    // A fixed one-to-one price.
+}
+
+impl State {
+    pub fn feature_enabled(&self, name: &str) -> bool {
+        self.features
+            .iter()
+            .find(|feature| feature.name == name)
+            .is_none_or(|feature| feature.enabled)
+    }
+}
+
+/// Config schema persisted before multiple securities were supported; used by migrations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
+    pub contract_admin: Addr,
+    pub security: String,
+    pub stablecoin: String,
+    #[serde(default)]
+    pub security_exponent: u32,
+    #[serde(default)]
+    pub stablecoin_exponent: u32,
+    #[serde(default)]
+    pub buy_cooldown: u64,
+    #[serde(default)]
+    pub private_trader_state: bool,
+    #[serde(default)]
+    pub partial_buy: bool,
+    #[serde(default)]
+    pub price_sources: Vec<PriceSource>,
+    #[serde(default)]
+    pub security_pool: Option<Addr>,
+    #[serde(default)]
+    pub stablecoin_pool: Option<Addr>,
+    #[serde(default)]
+    pub features: Vec<Feature>,
+    #[serde(default)]
+    pub interest_bps: u16,
+}
+
+impl LegacyState {
+    pub fn upgrade(self) -> State {
+        // Config saved before price sources were added always used the stub price.
+        let price_sources = if self.price_sources.is_empty() {
+            vec![PriceSource::Stub {}]
+        } else {
+            self.price_sources
+        };
+        let denom = self.security.clone();
+        let security_pools = self
+            .security_pool
+            .map(|address| MarkerPool { denom, address })
+            .into_iter()
+            .collect();
+        State {
+            contract_admin: self.contract_admin,
+            securities: vec![self.security],
+            stablecoin: self.stablecoin,
+            security_exponent: self.security_exponent,
+            stablecoin_exponent: self.stablecoin_exponent,
+            buy_cooldown: self.buy_cooldown,
+            private_trader_state: self.private_trader_state,
+            partial_buy: self.partial_buy,
+            price_sources,
+            security_pools,
+            stablecoin_pool: self.stablecoin_pool,
+            features: self.features,
+            interest_bps: self.interest_bps,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MarkerPool {
+    pub denom: String, // The marker denom.
+    pub address: Addr, // The marker address, which holds the pool.
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Feature {
+    pub name: String,  // The feature name, eg "loans".
+    pub enabled: bool, // Whether handlers allow the feature.
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TraderState {
     pub loan_cap: Uint128, // The max amount of stablecoin that can be loaned to this trader
     pub loans: Uint128,    // The current amount of stablecoin loaned to this trader
+    #[serde(default)]
+    pub last_buy_ts: u64, // The block time (seconds) of the trader's last buy
+    #[serde(default)]
+    pub address: String, // The trader address, since the bucket is keyed by canonical address
+    #[serde(default)]
+    pub interest_bps: u16, // The annual simple interest rate charged on loans, in basis points
+    #[serde(default)]
+    pub last_accrued: u64, // The block time (seconds) interest was last added to loans
+    #[serde(default)]
+    pub bad_debt: Uint128, // Loans left unpaid after the trader's stock was liquidated
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+pub fn legacy_config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, LegacyState> {
     singleton_read(storage, CONFIG_KEY)
 }
 
-pub fn trader_bucket(storage: &mut dyn Storage) -> Bucket<TraderState> {
+pub fn trader_bucket(storage: &mut dyn Storage) -> Bucket<'_, TraderState> {
     bucket(storage, TRADER_KEY)
 }
 
-pub fn trader_bucket_read(storage: &dyn Storage) -> ReadonlyBucket<TraderState> {
+pub fn trader_bucket_read(storage: &dyn Storage) -> ReadonlyBucket<'_, TraderState> {
     bucket_read(storage, TRADER_KEY)
 }
@@ -3,8 +3,8 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use orderbook::msg::{ExecuteMsg, InitMsg, QueryMsg};
-use orderbook::state::{AskOrder, BidOrder, State};
+use orderbook::msg::{ExecuteMsg, InitMsg, MigrateMsg, QueryMsg};
+use orderbook::state::{AskOrder, BidOrder, MarketStats, State};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,8 +15,10 @@ fn main() {
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
 
     export_schema(&schema_for!(AskOrder), &out_dir);
     export_schema(&schema_for!(BidOrder), &out_dir);
+    export_schema(&schema_for!(MarketStats), &out_dir);
     export_schema(&schema_for!(State), &out_dir);
 }
@@ -1,16 +1,32 @@
 use cosmwasm_std::{
-    coin, to_binary, BankMsg, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Order,
-    QueryResponse, Response, StdResult, Storage, Uint128,
+    coin, to_binary, Addr, BankMsg, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env, Fraction,
+    MessageInfo, Order, QueryResponse, Response, StdResult, Storage, Uint128,
 };
 
 use crate::error::ContractError;
-use crate::msg::{AskOrders, BidOrders, ExecuteMsg, InitMsg, Orderbook, QueryMsg};
+use crate::msg::{
+    AskOrders, BidOrders, Depth, ExecuteMsg, FoundOrder, Imbalance, InitMsg, MatchablePairsCount,
+    MigrateMsg, OrderAge, OrderEscrow, OrderSide, Orderbook, PriceLevel, QueryMsg, SimulatedFill,
+    SimulatedMatch, SpreadBps, UnmatchableOrder, UnmatchableOrders, UnmatchableReason,
+};
 use crate::state::{
-    ask_orders, ask_orders_read, bid_orders, bid_orders_read, config, config_read, AskOrder,
-    BidOrder, State,
+    ask_orders, ask_orders_read, bid_orders, bid_orders_read, config, config_read,
+    legacy_ask_orders_read, legacy_bid_orders_read, legacy_config_read, market_stats,
+    market_stats_read, AskOrder, BidOrder, State,
 };
 
+use cosmwasm_storage::ReadonlyBucket;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+// The max length of a bid or ask order id.
+const MAX_ORDER_ID_LEN: usize = 64;
+
+// The default and max number of orders returned by paginated order queries.
+const DEFAULT_PAGE_LIMIT: u32 = 30;
+const MAX_PAGE_LIMIT: u32 = 100;
 
 /// Initialize and save config state.
 pub fn instantiate(
@@ -19,12 +35,51 @@ pub fn instantiate(
     info: MessageInfo,
     msg: InitMsg,
 ) -> Result<Response, ContractError> {
+    // Default to selling nano-hash in 1 hash increments.
+    let ask_denom = msg.ask_denom.unwrap_or_else(|| "nhash".into());
+    let ask_increment = msg.ask_increment.unwrap_or(Uint128(1_000_000_000));
+
+    // Ensure the ask config is valid
+    if ask_increment.is_zero() {
+        return Err(ContractError::InvalidFunds {
+            message: "ask increment must be > 0".into(),
+        });
+    }
+    if ask_denom == msg.bid_denom {
+        return Err(ContractError::InvalidFunds {
+            message: "ask denom must not be the bid denom".into(),
+        });
+    }
+
+    // Ensure the fee config is valid, defaulting fees to the admin.
+    let fee_bps = msg.fee_bps.unwrap_or(0);
+    if fee_bps > 10_000 {
+        return Err(ContractError::InvalidFunds {
+            message: "fee bps must be <= 10000".into(),
+        });
+    }
+    let fee_collector = match msg.fee_collector {
+        Some(addr) => deps.api.addr_validate(&addr)?,
+        None => info.sender.clone(),
+    };
+
     // Create and store config state.
     let state = State {
-        ask_denom: "nhash".into(),             // nano-hash
-        ask_increment: Uint128(1_000_000_000), // 1 hash
+        ask_denom,
+        ask_increment,
         bid_denom: msg.bid_denom,
         contract_admin: info.sender,
+        min_fill: msg.min_fill.unwrap_or_else(Uint128::zero),
+        max_open_orders: msg.max_open_orders,
+        max_iterations: msg.max_iterations,
+        fee_bps,
+        fee_collector,
+        auto_match: msg.auto_match.unwrap_or(false),
+        min_bid_funds: msg.min_bid_funds.unwrap_or_else(Uint128::zero),
+        min_ask_funds: msg.min_ask_funds.unwrap_or_else(Uint128::zero),
+        paused: false,
+        denom_aliases: msg.denom_aliases.unwrap_or_default(),
+        entry_fee: msg.entry_fee.unwrap_or_else(Uint128::zero),
     };
     config(deps.storage).save(&state)?;
     Ok(Response::default())
@@ -38,27 +93,50 @@ pub fn execute(
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
     match msg {
-        ExecuteMsg::Bid { id, price } => try_bid(deps, env, info, id, price),
-        ExecuteMsg::Ask { id, price } => try_ask(deps, env, info, id, price),
-        ExecuteMsg::Match {} => try_match(deps, info, env),
+        ExecuteMsg::Bid {
+            id,
+            price,
+            expires_at,
+        } => try_bid(deps, env, info, id, Some(price), expires_at),
+        ExecuteMsg::Ask {
+            id,
+            price,
+            expires_at,
+        } => try_ask(deps, env, info, id, Some(price), expires_at),
+        ExecuteMsg::Match { max_matches } => try_match(deps, info, env, max_matches),
+        ExecuteMsg::CancelBid { id } => try_cancel_bid(deps, info, id),
+        ExecuteMsg::CancelAsk { id } => try_cancel_ask(deps, info, id),
+        ExecuteMsg::MarketBid { id } => try_bid(deps, env, info, id, None, None),
+        ExecuteMsg::MarketAsk { id } => try_ask(deps, env, info, id, None, None),
+        ExecuteMsg::SetPaused { paused } => try_set_paused(deps, info, paused),
+        ExecuteMsg::AmendBid { id, new_price } => try_amend_bid(deps, env, info, id, new_price),
+        ExecuteMsg::AmendAsk { id, new_price } => try_amend_ask(deps, env, info, id, new_price),
+        ExecuteMsg::ReapExpired {} => try_reap_expired(deps, env),
     }
 }
 
-// Validate then persist a bid order for later matching.
+// Validate then persist a bid order for later matching. Bids without a price are market bids.
 fn try_bid(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     id: String,
-    price: Uint128,
+    price: Option<Decimal>,
+    expires_at: Option<u64>,
 ) -> Result<Response, ContractError> {
+    // Ensure the order id is valid
+    validate_order_id(&id)?;
+
     // Ensure price is non-zero
-    if price.is_zero() {
+    if price.is_some_and(|price| price.is_zero()) {
         return Err(ContractError::InvalidPrice {
             message: "price must be > 0".into(),
         });
     }
 
+    // Ensure the order can rest until a later block
+    validate_expiration(&env, expires_at)?;
+
     // Ensure the correct funds where sent
     if info.funds.len() != 1 {
         return Err(ContractError::InvalidFunds {
@@ -67,8 +145,11 @@ fn try_bid(
     }
     let funds = info.funds[0].clone();
 
-    // Load config state
+    // Load config state, ensuring new orders are allowed.
     let state = config_read(deps.storage).load()?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
 
     // Ensure the funds are valid
     if funds.amount.is_zero() {
@@ -85,82 +166,242 @@ fn try_bid(
         });
     }
 
+    // Take the entry fee out of the bid funds, escrowing the rest.
+    if funds.amount <= state.entry_fee {
+        return Err(ContractError::InvalidFunds {
+            message: format!("bid amount must be > the entry fee: {}", state.entry_fee),
+        });
+    }
+    let funds = coin(funds.amount.u128() - state.entry_fee.u128(), funds.denom);
+    if funds.amount < state.min_bid_funds {
+        return Err(ContractError::OrderTooSmall {
+            min: state.min_bid_funds,
+        });
+    }
+
     // Admin is not allowed bid hash, only execute the matching algorithm.
     if info.sender == state.contract_admin {
         return Err(ContractError::Unauthorized {});
     }
 
+    // Ensure there's room in the orderbook for another order.
+    ensure_book_not_full(deps.storage, &state)?;
+
+    // Market bids are priced by the asks they match, so reject them if there are none to match.
+    if price.is_none() && !has_limit_asks(deps.storage)? {
+        return Err(ContractError::NoCounterOrders {});
+    }
+
     // Ensure an order with the given ID doesn't already exist.
     let order_key = id.as_bytes();
     let mut book = bid_orders(deps.storage);
-    if book.may_load(&order_key)?.is_some() {
+    if book.may_load(order_key)?.is_some() {
         return Err(ContractError::DuplicateBid { id: id.clone() });
     }
 
-    // Calculate and verify buy proceeds.
-    let num = funds.amount.u128() * state.ask_increment.u128();
-    if num % price.u128() != 0 {
-        return Err(ContractError::InvalidFunds {
-            message: "bid price must yield an integral for proceeds".into(),
-        });
-    }
-    let proceeds = Uint128(num / price.u128());
-    if proceeds.u128() % state.ask_increment.u128() != 0 {
-        deps.api.debug(&format!("proceeds={:?}", proceeds));
-        return Err(ContractError::InvalidFunds {
-            message: "funds must yield a bid amount in the required increments".into(),
-        });
-    }
+    // Calculate buy proceeds. Market bid proceeds are calculated when matched.
+    let proceeds = match price {
+        Some(price) => bid_proceeds(funds.amount, price, &state)?,
+        None => Uint128::zero(),
+    };
 
     // Persist bid order
     book.save(
-        &order_key,
+        order_key,
         &BidOrder {
             id: id.clone(),
-            price,
+            price: price.unwrap_or_else(Decimal::zero),
             ts: env.block.time.nanos() / 1_000_000_000, // use seconds
+            height: env.block.height,
             bidder: info.sender,
             funds: funds.amount,
             funds_denom: funds.denom,
             proceeds,
+            proceeds_denom: state.ask_denom.clone(),
+            market: price.is_none(),
+            expires_at,
         },
     )?;
 
     // Create response and add ID to outgoing SC `wasm` event
     let mut res = Response::new();
     res.add_attribute("action", "orderbook.bid");
+    res.add_attribute("market", price.is_none().to_string());
+    res.add_attribute("id", id.clone());
+    collect_entry_fee(&state, &mut res);
+
+    // Match the new bid and any other crossing orders from earlier blocks right away if auto
+    // match is enabled.
+    if state.auto_match {
+        match_book(deps, &env, &state, Some(Incoming::Bid(&id)), None, &mut res)?;
+    }
+    Ok(res)
+}
+
+// Calculate buy proceeds for the whole increments the funds can buy, rounding down. Any funds left
+// over are refunded once the bid is filled.
+fn bid_proceeds(funds: Uint128, price: Decimal, state: &State) -> Result<Uint128, ContractError> {
+    let units = units_at_price(funds, price);
+    if units == 0 {
+        return Err(ContractError::InvalidFunds {
+            message: "bid funds must buy at least one increment at the bid price".into(),
+        });
+    }
+    Ok(Uint128(units * state.ask_increment.u128()))
+}
+
+// Calculate sell proceeds for ask funds in whole increments. Proceeds must be a whole amount, since
+// any fraction couldn't be paid and the asker would be left short.
+fn ask_proceeds(funds: Uint128, price: Decimal, state: &State) -> Result<Uint128, ContractError> {
+    let units = Uint128(funds.u128() / state.ask_increment.u128());
+    let proceeds = units * price;
+    if !price.is_zero() && proceeds.multiply_ratio(price.denominator(), price.numerator()) != units
+    {
+        return Err(ContractError::InvalidFunds {
+            message: "ask price must yield an integral for proceeds".into(),
+        });
+    }
+    Ok(proceeds)
+}
+
+// Re-price a resting bid, recalculating what its remaining funds buy. The bid loses its time
+// priority, as if it had been cancelled and placed again.
+fn try_amend_bid(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    new_price: Decimal,
+) -> Result<Response, ContractError> {
+    // Ensure price is non-zero
+    if new_price.is_zero() {
+        return Err(ContractError::InvalidPrice {
+            message: "price must be > 0".into(),
+        });
+    }
+
+    // Load config state, ensuring order changes are allowed.
+    let state = config_read(deps.storage).load()?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    // Ensure the bid exists and is still open
+    let order_key = id.as_bytes();
+    let mut book = bid_orders(deps.storage);
+    let mut order = match book.may_load(order_key)? {
+        Some(order) => order,
+        None => return Err(ContractError::OrderNotFound { id }),
+    };
+    if order.is_closed() {
+        return Err(ContractError::BidClosed {});
+    }
+
+    // Only the bidder can amend their order, and market bids have no price to amend.
+    if info.sender != order.bidder {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.market {
+        return Err(ContractError::InvalidPrice {
+            message: "market orders can't be amended".into(),
+        });
+    }
+
+    // Persist the re-priced bid, moving it to the back of the queue at its new price.
+    order.proceeds = bid_proceeds(order.funds, new_price, &state)?;
+    order.price = new_price;
+    order.ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    order.height = env.block.height;
+    book.save(order_key, &order)?;
+
+    // Create response
+    let mut res = Response::new();
+    res.add_attribute("action", "orderbook.amend_bid");
+    res.add_attribute("id", id);
+    res.add_attribute("price", new_price.to_string());
+    Ok(res)
+}
+
+// Remove a bid order from the orderbook, refunding any unmatched funds to the bidder.
+fn try_cancel_bid(deps: DepsMut, info: MessageInfo, id: String) -> Result<Response, ContractError> {
+    // Ensure the bid exists and is still open
+    let order_key = id.as_bytes();
+    let mut book = bid_orders(deps.storage);
+    let order = match book.may_load(order_key)? {
+        Some(order) => order,
+        None => return Err(ContractError::OrderNotFound { id }),
+    };
+    if order.is_closed() {
+        return Err(ContractError::BidClosed {});
+    }
+
+    // Only the bidder can cancel their order
+    if info.sender != order.bidder {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Remove the bid order
+    book.remove(order_key);
+
+    // Create response, refunding the funds remaining after any partial matches
+    let mut res = Response::new();
+    res.add_message(BankMsg::Send {
+        amount: vec![coin(order.funds.u128(), order.funds_denom)],
+        to_address: order.bidder.to_string(),
+    });
+    res.add_attribute("action", "orderbook.cancel_bid");
     res.add_attribute("id", id);
     Ok(res)
 }
 
-// Validate then persist a ask order for later matching.
+// Validate then persist a ask order for later matching. Asks without a price are market asks.
 fn try_ask(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     id: String,
-    price: Uint128,
+    price: Option<Decimal>,
+    expires_at: Option<u64>,
 ) -> Result<Response, ContractError> {
+    // Ensure the order id is valid
+    validate_order_id(&id)?;
+
     // Ensure price is non-zero
-    if price.is_zero() {
+    if price.is_some_and(|price| price.is_zero()) {
         return Err(ContractError::InvalidPrice {
             message: "price must be > 0".into(),
         });
     }
 
-    // Ensure the correct number of funds where sent.
-    if info.funds.len() != 1 {
+    // Ensure the order can rest until a later block
+    validate_expiration(&env, expires_at)?;
+
+    // Load config state.
+    let state = config_read(deps.storage).load()?;
+
+    // Ensure the correct number of funds where sent, including any entry fee.
+    let (funds, fee) = split_ask_funds(&info.funds, &state)?;
+    if fee != state.entry_fee {
         return Err(ContractError::InvalidFunds {
-            message: "invalid number of ask funds provided".into(),
+            message: format!(
+                "ask entry fee must be {}{}: got {}",
+                state.entry_fee, state.bid_denom, fee
+            ),
         });
     }
-    let funds = info.funds[0].clone();
 
-    // Load config state
-    let state = config_read(deps.storage).load()?;
+    // Ensure new orders are allowed.
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
 
     // Ensure the funds are valid (ie at least 1 hash in 1hash increments)
-    if funds.amount.is_zero() || funds.amount.u128() % state.ask_increment.u128() != 0 {
+    if funds.amount.is_zero()
+        || !funds
+            .amount
+            .u128()
+            .is_multiple_of(state.ask_increment.u128())
+    {
         return Err(ContractError::InvalidFunds {
             message: format!(
                 "ask amount must be > 0 in the required increments: got {}",
@@ -176,96 +417,500 @@ fn try_ask(
             ),
         });
     }
+    if funds.amount < state.min_ask_funds {
+        return Err(ContractError::OrderTooSmall {
+            min: state.min_ask_funds,
+        });
+    }
 
     // Admin is not allowed sell hash, only execute the matching algorithm.
     if info.sender == state.contract_admin {
         return Err(ContractError::Unauthorized {});
     }
 
+    // Ensure there's room in the orderbook for another order.
+    ensure_book_not_full(deps.storage, &state)?;
+
+    // Market asks are priced by the bids they match, so reject them if there are none to match.
+    if price.is_none() && !has_limit_bids(deps.storage)? {
+        return Err(ContractError::NoCounterOrders {});
+    }
+
     // Ensure an order with the given ID doesn't already exist.
     let order_key = id.as_bytes();
     let mut book = ask_orders(deps.storage);
-    if book.may_load(&order_key)?.is_some() {
+    if book.may_load(order_key)?.is_some() {
         return Err(ContractError::DuplicateAsk { id: id.clone() });
     }
 
-    // Calculate sell proceeds
-    let proceeds = funds.amount * Decimal::from_ratio(price, state.ask_increment);
+    // Calculate sell proceeds. Market ask proceeds are calculated when matched.
+    let price = price.unwrap_or_else(Decimal::zero);
+    let proceeds = ask_proceeds(funds.amount, price, &state)?;
 
     // Persist ask order
     book.save(
-        &order_key,
+        order_key,
         &AskOrder {
             id: id.clone(),
             price,
             ts: env.block.time.nanos() / 1_000_000_000, // use seconds
+            height: env.block.height,
             asker: info.sender,
             funds: funds.amount,
             funds_denom: funds.denom,
             proceeds,
+            proceeds_denom: state.bid_denom.clone(),
+            market: price.is_zero(),
+            expires_at,
         },
     )?;
 
     // Create response and add ID to outgoing SC `wasm` event
     let mut res = Response::new();
     res.add_attribute("action", "orderbook.ask");
+    res.add_attribute("market", price.is_zero().to_string());
+    res.add_attribute("id", id.clone());
+    collect_entry_fee(&state, &mut res);
+
+    // Match the new ask and any other crossing orders from earlier blocks right away if auto
+    // match is enabled.
+    if state.auto_match {
+        match_book(deps, &env, &state, Some(Incoming::Ask(&id)), None, &mut res)?;
+    }
+    Ok(res)
+}
+
+// Split the funds sent with an ask into the funds to sell and the entry fee paid in the bid denom.
+fn split_ask_funds(funds: &[Coin], state: &State) -> Result<(Coin, Uint128), ContractError> {
+    let (fees, sold): (Vec<&Coin>, Vec<&Coin>) = funds
+        .iter()
+        .partition(|funds| !state.entry_fee.is_zero() && funds.denom == state.bid_denom);
+    if sold.len() != 1 || fees.len() > 1 {
+        return Err(ContractError::InvalidFunds {
+            message: "invalid number of ask funds provided".into(),
+        });
+    }
+    let fee = fees.first().map_or_else(Uint128::zero, |fee| fee.amount);
+    Ok((sold[0].clone(), fee))
+}
+
+// Send the entry fee for a new order to the fee collector.
+fn collect_entry_fee(state: &State, res: &mut Response) {
+    if !state.entry_fee.is_zero() {
+        res.add_message(BankMsg::Send {
+            amount: vec![coin(state.entry_fee.u128(), state.bid_denom.clone())],
+            to_address: state.fee_collector.to_string(),
+        });
+    }
+}
+
+// Re-price a resting ask, recalculating the proceeds for its remaining funds. The ask loses its
+// time priority, as if it had been cancelled and placed again.
+fn try_amend_ask(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    new_price: Decimal,
+) -> Result<Response, ContractError> {
+    // Ensure price is non-zero
+    if new_price.is_zero() {
+        return Err(ContractError::InvalidPrice {
+            message: "price must be > 0".into(),
+        });
+    }
+
+    // Load config state, ensuring order changes are allowed.
+    let state = config_read(deps.storage).load()?;
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
+
+    // Ensure the ask exists and is still open
+    let order_key = id.as_bytes();
+    let mut book = ask_orders(deps.storage);
+    let mut order = match book.may_load(order_key)? {
+        Some(order) => order,
+        None => return Err(ContractError::OrderNotFound { id }),
+    };
+    if order.is_closed() {
+        return Err(ContractError::AskClosed {});
+    }
+
+    // Only the asker can amend their order, and market asks have no price to amend.
+    if info.sender != order.asker {
+        return Err(ContractError::Unauthorized {});
+    }
+    if order.market {
+        return Err(ContractError::InvalidPrice {
+            message: "market orders can't be amended".into(),
+        });
+    }
+
+    // Ensure the remaining funds are still in whole increments
+    if !order
+        .funds
+        .u128()
+        .is_multiple_of(state.ask_increment.u128())
+    {
+        return Err(ContractError::InvalidFunds {
+            message: format!(
+                "ask amount must be > 0 in the required increments: got {}",
+                order.funds
+            ),
+        });
+    }
+
+    // Persist the re-priced ask, moving it to the back of the queue at its new price.
+    order.proceeds = ask_proceeds(order.funds, new_price, &state)?;
+    order.price = new_price;
+    order.ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    order.height = env.block.height;
+    book.save(order_key, &order)?;
+
+    // Create response
+    let mut res = Response::new();
+    res.add_attribute("action", "orderbook.amend_ask");
+    res.add_attribute("id", id);
+    res.add_attribute("price", new_price.to_string());
+    Ok(res)
+}
+
+// Remove an ask order from the orderbook, returning any unmatched funds to the asker.
+fn try_cancel_ask(deps: DepsMut, info: MessageInfo, id: String) -> Result<Response, ContractError> {
+    // Ensure the ask exists and is still open
+    let order_key = id.as_bytes();
+    let mut book = ask_orders(deps.storage);
+    let order = match book.may_load(order_key)? {
+        Some(order) => order,
+        None => return Err(ContractError::OrderNotFound { id }),
+    };
+    if order.is_closed() {
+        return Err(ContractError::AskClosed {});
+    }
+
+    // Only the asker can cancel their order
+    if info.sender != order.asker {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Remove the ask order
+    book.remove(order_key);
+
+    // Create response, returning the funds remaining after any partial matches
+    let mut res = Response::new();
+    res.add_message(BankMsg::Send {
+        amount: vec![coin(order.funds.u128(), order.funds_denom)],
+        to_address: order.asker.to_string(),
+    });
+    res.add_attribute("action", "orderbook.cancel_ask");
     res.add_attribute("id", id);
     Ok(res)
 }
 
+// Remove all expired orders, refunding their remaining funds. Anyone can reap, even while the
+// orderbook is paused, since it only returns funds to their owners.
+fn try_reap_expired(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    let mut res = Response::new();
+    let reaped = reap_expired(deps.storage, ts, &mut res)?;
+    res.add_attribute("action", "orderbook.reap_expired");
+    res.add_attribute("reaped", reaped.to_string());
+    Ok(res)
+}
+
+// Remove orders that expired before the given block time, adding refunds for their remaining funds
+// and an event for each order to the response.
+fn reap_expired(
+    storage: &mut dyn Storage,
+    ts: u64,
+    res: &mut Response,
+) -> Result<u32, ContractError> {
+    let mut reaped = 0;
+    let bids: Vec<BidOrder> = get_orders(bid_orders_read(storage))?
+        .into_iter()
+        .filter(|bid| bid.is_expired(ts))
+        .collect();
+    for bid in bids {
+        bid_orders(storage).remove(bid.id.as_bytes());
+        if !bid.funds.is_zero() {
+            res.add_message(BankMsg::Send {
+                amount: vec![coin(bid.funds.u128(), bid.funds_denom)],
+                to_address: bid.bidder.to_string(),
+            });
+        }
+        res.add_attribute("orderbook.expired", bid.id);
+        reaped += 1;
+    }
+    let asks: Vec<AskOrder> = get_orders(ask_orders_read(storage))?
+        .into_iter()
+        .filter(|ask| ask.is_expired(ts))
+        .collect();
+    for ask in asks {
+        ask_orders(storage).remove(ask.id.as_bytes());
+        if !ask.funds.is_zero() {
+            res.add_message(BankMsg::Send {
+                amount: vec![coin(ask.funds.u128(), ask.funds_denom)],
+                to_address: ask.asker.to_string(),
+            });
+        }
+        res.add_attribute("orderbook.expired", ask.id);
+        reaped += 1;
+    }
+    Ok(reaped)
+}
+
+// Halt or resume new orders and matching. Cancels are always allowed so users can exit.
+fn try_set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
+        if info.sender != state.contract_admin {
+            return Err(ContractError::Unauthorized {});
+        }
+        state.paused = paused;
+        Ok(state)
+    })?;
+    let mut res = Response::new();
+    res.add_attribute("action", "orderbook.set_paused");
+    res.add_attribute("paused", paused.to_string());
+    Ok(res)
+}
+
 // Execute the match algorithm.
-fn try_match(deps: DepsMut, info: MessageInfo, env: Env) -> Result<Response, ContractError> {
+fn try_match(
+    deps: DepsMut,
+    info: MessageInfo,
+    env: Env,
+    max_matches: Option<u32>,
+) -> Result<Response, ContractError> {
     // Load config state
     let state = config_read(deps.storage).load()?;
 
-    // Only the admin can execute matching.
+    // Only the admin can execute matching, and only while the orderbook isn't paused.
     if info.sender != state.contract_admin {
         return Err(ContractError::Unauthorized {});
     }
+    if state.paused {
+        return Err(ContractError::Paused {});
+    }
 
-    // Create aggregate response and get the BFT time of the current block.
+    // Create aggregate response and run the matching algorithm.
     let mut res = Response::new();
+    match_book(deps, &env, &state, None, max_matches, &mut res)?;
+
+    // Done
+    Ok(res)
+}
+
+// An order placed in the current block that auto match pairs with orders from earlier blocks.
+enum Incoming<'a> {
+    Bid(&'a str),
+    Ask(&'a str),
+}
+
+// Match crossing orders placed before the current block, plus the incoming order if given, adding
+// bank sends and match events to the given response. Matching stops early if max_matches is
+// reached.
+fn match_book(
+    deps: DepsMut,
+    env: &Env,
+    state: &State,
+    incoming: Option<Incoming>,
+    max_matches: Option<u32>,
+    res: &mut Response,
+) -> Result<(), ContractError> {
+    // Remove expired orders before pairing, so they're refunded instead of matched.
+    let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    reap_expired(deps.storage, ts, res)?;
+
+    // Pair orders, then persist the order state after each match.
+    let fills = pair_orders(deps.as_ref(), env, state, incoming, max_matches, res)?;
+    if fills.is_empty() {
+        return Ok(());
+    }
+    let mut stats = market_stats_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    for fill in fills {
+        stats.total_nhash_matched = stats
+            .total_nhash_matched
+            .saturating_add(fill.fill_ask_amount);
+        stats.total_stablecoin_matched = stats
+            .total_stablecoin_matched
+            .saturating_add(fill.fill_bid_amount);
+        stats.match_count = stats.match_count.saturating_add(1);
+        update_ask_order(deps.storage, fill.ask)?;
+        update_bid_order(deps.storage, fill.bid)?;
+    }
+    market_stats(deps.storage).save(&stats)?;
+    Ok(())
+}
+
+// A match made by the pairing algorithm, with the state of both orders after the match.
+struct Fill {
+    pub bid: BidOrder,
+    pub ask: AskOrder,
+    pub fill_price: Option<Decimal>,
+    pub fill_ask_amount: Uint128,
+    pub fill_bid_amount: Uint128,
+}
+
+// Pair crossing orders placed before the current block in price-time order, without persisting
+// anything. The incoming order, if given, is paired too, so it can only match orders from earlier
+// blocks. Bank sends and match events are added to the given response; simulations pass a
+// response they discard.
+fn pair_orders(
+    deps: Deps,
+    env: &Env,
+    state: &State,
+    incoming: Option<Incoming>,
+    max_matches: Option<u32>,
+    res: &mut Response,
+) -> Result<Vec<Fill>, ContractError> {
+    // Get the BFT time of the current block.
     let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
 
-    // Query and filter ask orders
-    let asks: Vec<AskOrder> = get_ask_orders(deps.as_ref())?
+    // Query and filter orders, ignoring orders in the current block other than the incoming order,
+    // and expired orders. Bids are updated in place as they're matched, so each ask sees what
+    // earlier asks left behind.
+    let asks: Vec<AskOrder> = get_ask_orders(deps)?
+        .into_iter()
+        .filter(|ask| {
+            let incoming = matches!(incoming, Some(Incoming::Ask(id)) if id == ask.id);
+            (ask.ts < ts || incoming) && !ask.is_expired(ts)
+        })
+        .collect();
+    let mut bids: Vec<BidOrder> = get_bid_orders(deps)?
         .into_iter()
-        .filter(|ask| ask.ts < ts) // Ignore asks in the current block
+        .filter(|bid| {
+            let incoming = matches!(incoming, Some(Incoming::Bid(id)) if id == bid.id);
+            (bid.ts < ts || incoming) && !bid.is_expired(ts)
+        })
         .collect();
 
+    // Track the bid/ask pairs considered, so matching can stop before exhausting gas.
+    let mut iterations: u64 = 0;
+    let mut fills: Vec<Fill> = vec![];
+
     // Match each ask in price/time order
-    for ask in asks {
+    'asks: for ask in asks {
         // Create an updatable ask order
         let mut ask = ask;
 
-        // Look for bid orders with a price >= ask price, ignoring bids in the current block.
-        let bids: Vec<BidOrder> = get_bid_orders(deps.as_ref())?
-            .into_iter()
-            .filter(|bid| bid.price >= ask.price && bid.ts < ts)
-            .collect();
+        // Match ask with any/all open bid orders that cross it
+        for slot in bids.iter_mut() {
+            if slot.is_closed() || !crosses(slot, &ask) {
+                continue;
+            }
+            let bid = slot.clone();
+
+            // Stop early if the iteration cap is hit; the admin can match again to continue.
+            if state.max_iterations.is_some_and(|max| iterations >= max) {
+                res.add_attribute("orderbook.max_iterations_reached", "true");
+                break 'asks;
+            }
+            iterations += 1;
+
+            // Don't let an account trade with itself; both orders are left resting.
+            if bid.bidder == ask.asker {
+                res.add_attribute("orderbook.self_trade_skipped", ask.id.clone());
+                continue;
+            }
+
+            // Market orders trade at the price of the limit order they're matched with.
+            let (mut bid, held) = match price_market_bid(bid, &ask, state) {
+                Some(priced) => priced,
+                None => continue, // Can't afford a single increment at this price
+            };
+            let mut ask_priced = match price_market_ask(ask.clone(), &bid, state) {
+                Some(priced) => priced,
+                None => continue, // The proceeds at this price aren't a whole amount
+            };
+
+            // Skip pairs that would only fill a dust amount, leaving both orders resting.
+            if bid.proceeds.min(ask_priced.funds) < state.min_fill {
+                continue;
+            }
 
-        // Match ask with any/all bid orders
-        for bid in bids {
-            // Execute match
-            let match_res = match_orders(bid, ask.clone())?;
+            // Stop before this match if the cap is hit. Every match so far is persisted, so the
+            // admin can match again to continue.
+            if max_matches.is_some_and(|max| fills.len() as u32 >= max) {
+                res.add_attribute("orderbook.matches_remaining", "true");
+                break 'asks;
+            }
+
+            // Trade at the execution price, crediting any price improvement to the later order.
+            let bid_improvement = credit_price_improvement(&mut bid, &mut ask_priced, state);
+
+            // Execute match, keeping pre-match amounts to report what was filled. Refunds of
+            // leftover bid funds aren't part of the fill.
+            let bid_proceeds = bid.proceeds;
+            let mut match_res = match_orders(bid, ask_priced, state)?;
+            let fill_bid_amount = match_res.paid;
+            let fill_ask_amount = Uint128(bid_proceeds.u128() - match_res.bid.proceeds.u128());
 
             // Add bank sends to outgoing response
             for msg in match_res.msgs {
                 res.add_message(msg);
             }
 
+            // Refund the bidder for any price improvement on the filled amount.
+            if !bid_improvement.is_zero() {
+                res.add_message(BankMsg::Send {
+                    amount: vec![coin(
+                        bid_improvement.u128(),
+                        match_res.bid.funds_denom.clone(),
+                    )],
+                    to_address: match_res.bid.bidder.to_string(),
+                });
+            }
+
+            // Return any market bid funds that were held back from the match. Once the bid has
+            // bought all it can afford at this price, refund them so the order closes.
+            if match_res.bid.proceeds.is_zero() && !held.is_zero() {
+                res.add_message(BankMsg::Send {
+                    amount: vec![coin(held.u128(), match_res.bid.funds_denom.clone())],
+                    to_address: match_res.bid.bidder.to_string(),
+                });
+            } else {
+                match_res.bid.funds += held;
+            }
+
             // Add a match event attribute to outgoing response
             res.add_attribute(
                 "orderbook.match",
                 format!("bid:{},ask:{}", match_res.bid.id, match_res.ask.id),
             );
 
-            // Update ask for the next iteration
-            ask = match_res.ask.clone();
+            // Add fill details so trades can be reconstructed off-chain. The price is per ask
+            // increment, derived from the amounts that moved.
+            let fill_price = if fill_ask_amount.is_zero() {
+                None
+            } else {
+                Some(Decimal::from_ratio(
+                    fill_bid_amount.u128() * state.ask_increment.u128(),
+                    fill_ask_amount,
+                ))
+            };
+            if let Some(fill_price) = fill_price {
+                res.add_attribute("orderbook.fill_price", fill_price.to_string());
+            }
+            res.add_attribute("orderbook.fill_ask_amount", fill_ask_amount);
+            res.add_attribute("orderbook.fill_bid_amount", fill_bid_amount);
 
-            // Persist order state
-            update_ask_order(deps.storage, match_res.ask)?;
-            update_bid_order(deps.storage, match_res.bid)?;
+            // Update orders for the next iteration
+            ask = match_res.ask.clone();
+            *slot = match_res.bid.clone();
+            fills.push(Fill {
+                bid: match_res.bid,
+                ask: match_res.ask,
+                fill_price,
+                fill_ask_amount,
+                fill_bid_amount,
+            });
 
             // Stop if ask is closed
             if ask.is_closed() {
@@ -274,8 +919,105 @@ fn try_match(deps: DepsMut, info: MessageInfo, env: Env) -> Result<Response, Con
         }
     }
 
-    // Done
-    Ok(res)
+    Ok(fills)
+}
+
+// Determine whether a bid and ask cross. Market orders cross any limit order on the other side,
+// but two market orders can't match because neither has a price.
+fn crosses(bid: &BidOrder, ask: &AskOrder) -> bool {
+    match (bid.market, ask.market) {
+        (false, false) => bid.price >= ask.price,
+        (true, true) => false,
+        _ => true,
+    }
+}
+
+// Determine the price a crossing bid and ask trade at. The earlier order set its price first, so
+// the trade executes at that price. Orders placed at the same time trade at the ask price.
+fn execution_price(bid: &BidOrder, ask: &AskOrder) -> Decimal {
+    if bid.ts < ask.ts {
+        bid.price
+    } else {
+        ask.price
+    }
+}
+
+// Adjust a crossing bid and ask so the filled amount trades at the execution price. If the bid
+// price is improved on, its funds are reduced by the improvement, which is returned so it can be
+// refunded to the bidder. If the ask price is improved on, the ask proceeds are increased so the
+// asker is paid the improvement when matched.
+fn credit_price_improvement(bid: &mut BidOrder, ask: &mut AskOrder, state: &State) -> Uint128 {
+    let price = execution_price(bid, ask);
+    let units = bid.proceeds.min(ask.funds).u128() / state.ask_increment.u128();
+    if bid.price > price {
+        let improvement = Uint128(units) * (bid.price - price);
+        bid.funds = Uint128(bid.funds.u128() - improvement.u128());
+        return improvement;
+    }
+    if price > ask.price {
+        ask.proceeds += Uint128(units) * (price - ask.price);
+    }
+    Uint128::zero()
+}
+
+// Price a market bid at the ask price, holding back any funds that can't buy a whole increment.
+// Returns the priced bid and held funds, or None if the bid can't afford a single increment.
+fn price_market_bid(bid: BidOrder, ask: &AskOrder, state: &State) -> Option<(BidOrder, Uint128)> {
+    if !bid.market {
+        return Some((bid, Uint128::zero()));
+    }
+    let units = units_at_price(bid.funds, ask.price);
+    if units == 0 {
+        return None;
+    }
+    let mut bid = bid;
+    let funds = Uint128(units) * ask.price;
+    let held = Uint128(bid.funds.u128() - funds.u128());
+    bid.price = ask.price;
+    bid.funds = funds;
+    bid.proceeds = Uint128(units * state.ask_increment.u128());
+    Some((bid, held))
+}
+
+// Price a market ask at the bid price. Returns None if the proceeds at that price wouldn't be a
+// whole amount, since the asker would be left short, as with limit asks.
+fn price_market_ask(ask: AskOrder, bid: &BidOrder, state: &State) -> Option<AskOrder> {
+    if !ask.market {
+        return Some(ask);
+    }
+    let mut ask = ask;
+    ask.proceeds = ask_proceeds(ask.funds, bid.price, state).ok()?;
+    ask.price = bid.price;
+    Some(ask)
+}
+
+// The number of whole ask increments funds can buy at a price, rounded down.
+fn units_at_price(funds: Uint128, price: Decimal) -> u128 {
+    funds
+        .multiply_ratio(price.denominator(), price.numerator())
+        .u128()
+}
+
+// Determine whether there are any limit bids a market ask could be priced against.
+fn has_limit_bids(storage: &dyn Storage) -> StdResult<bool> {
+    for item in bid_orders_read(storage).range(None, None, Order::Ascending) {
+        let (_, bid) = item?;
+        if !bid.market {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+// Determine whether there are any limit asks a market bid could be priced against.
+fn has_limit_asks(storage: &dyn Storage) -> StdResult<bool> {
+    for item in ask_orders_read(storage).range(None, None, Order::Ascending) {
+        let (_, ask) = item?;
+        if !ask.market {
+            return Ok(true);
+        }
+    }
+    Ok(false)
 }
 
 // The return type for matching orders
@@ -283,10 +1025,11 @@ struct MatchResult {
     pub bid: BidOrder,
     pub ask: AskOrder,
     pub msgs: Vec<CosmosMsg>,
+    pub paid: Uint128, // The bid denom paid for the ask, including any fee, but not refunds
 }
 
 // Match a bid order with a ask order.
-fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractError> {
+fn match_orders(bid: BidOrder, ask: AskOrder, state: &State) -> Result<MatchResult, ContractError> {
     // Validate orders are still open
     if bid.is_closed() {
         return Err(ContractError::BidClosed {});
@@ -303,17 +1046,15 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
     let mut msgs: Vec<CosmosMsg> = Vec::new();
 
     // Process stablecoin transfer to asker
+    let paid = ask.proceeds.min(bid.funds);
     match ask.proceeds.cmp(&bid.funds) {
         Ordering::Less => {
             // Transfer ask.proceeds funds to asker
-            let amt = coin(ask.proceeds.u128(), bid.funds_denom.clone());
-            msgs.push(
-                BankMsg::Send {
-                    amount: vec![amt],
-                    to_address: ask.asker.to_string(),
-                }
-                .into(),
-            );
+            msgs.extend(settle(
+                coin(ask.proceeds.u128(), bid.funds_denom.clone()),
+                &ask.asker,
+                state,
+            ));
             // Reduce bid.funds by ask.proceeds
             bid.funds = Uint128(bid.funds.u128() - ask.proceeds.u128());
             // Set ask.proceeds to zero
@@ -321,14 +1062,11 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
         }
         _ => {
             // Transfer bid.funds to asker
-            let amt = coin(bid.funds.u128(), bid.funds_denom.clone());
-            msgs.push(
-                BankMsg::Send {
-                    amount: vec![amt],
-                    to_address: ask.asker.to_string(),
-                }
-                .into(),
-            );
+            msgs.extend(settle(
+                coin(bid.funds.u128(), bid.funds_denom.clone()),
+                &ask.asker,
+                state,
+            ));
             // Reduce ask.proceeds by bid.funds
             ask.proceeds = Uint128(ask.proceeds.u128() - bid.funds.u128());
             // Set bid.funds to zero
@@ -340,14 +1078,11 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
     match bid.proceeds.cmp(&ask.funds) {
         Ordering::Less => {
             // Transfer bid.proceeds funds to bidder
-            let amt = coin(bid.proceeds.u128(), ask.funds_denom.clone());
-            msgs.push(
-                BankMsg::Send {
-                    amount: vec![amt],
-                    to_address: bid.bidder.to_string(),
-                }
-                .into(),
-            );
+            msgs.extend(settle(
+                coin(bid.proceeds.u128(), ask.funds_denom.clone()),
+                &bid.bidder,
+                state,
+            ));
             // Reduce ask.funds by bid.proceeds
             ask.funds = Uint128(ask.funds.u128() - bid.proceeds.u128());
             // Set bid.proceeds to zero
@@ -355,14 +1090,11 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
         }
         _ => {
             // Transfer ask.funds to bidder
-            let amt = coin(ask.funds.u128(), ask.funds_denom.clone());
-            msgs.push(
-                BankMsg::Send {
-                    amount: vec![amt],
-                    to_address: bid.bidder.to_string(),
-                }
-                .into(),
-            );
+            msgs.extend(settle(
+                coin(ask.funds.u128(), ask.funds_denom.clone()),
+                &bid.bidder,
+                state,
+            ));
             // Reduce bid.proceeds by ask.funds
             bid.proceeds = Uint128(bid.proceeds.u128() - ask.funds.u128());
             // Set ask.funds to zero
@@ -370,6 +1102,19 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
         }
     }
 
+    // If the bid amount was met but funds were left over from rounding, refund them.
+    if bid.proceeds.is_zero() && !bid.funds.is_zero() {
+        let refund = coin(bid.funds.u128(), bid.funds_denom.clone());
+        msgs.push(
+            BankMsg::Send {
+                amount: vec![refund],
+                to_address: bid.bidder.to_string(),
+            }
+            .into(),
+        );
+        bid.funds = Uint128::zero();
+    }
+
     // If the ask amount was met but not all funds were required, refund them.
     if ask.proceeds.is_zero() && !ask.funds.is_zero() {
         let refund = coin(ask.funds.u128(), ask.funds_denom.clone());
@@ -383,7 +1128,39 @@ fn match_orders(bid: BidOrder, ask: AskOrder) -> Result<MatchResult, ContractErr
         ask.funds = Uint128::zero();
     }
 
-    Ok(MatchResult { bid, ask, msgs })
+    Ok(MatchResult {
+        bid,
+        ask,
+        msgs,
+        paid,
+    })
+}
+
+// Send the counterparty their side of a match, less the trading fee. The fee is rounded down and
+// taken out of the amount, so the total sent never exceeds what was escrowed. Zero amounts aren't
+// sent, since the bank module rejects them.
+fn settle(amount: Coin, to: &Addr, state: &State) -> Vec<CosmosMsg> {
+    let fee = amount.amount.u128() * state.fee_bps as u128 / 10_000;
+    let mut msgs: Vec<CosmosMsg> = vec![];
+    if amount.amount.u128() > fee {
+        msgs.push(
+            BankMsg::Send {
+                amount: vec![coin(amount.amount.u128() - fee, amount.denom.clone())],
+                to_address: to.to_string(),
+            }
+            .into(),
+        );
+    }
+    if fee > 0 {
+        msgs.push(
+            BankMsg::Send {
+                amount: vec![coin(fee, amount.denom)],
+                to_address: state.fee_collector.to_string(),
+            }
+            .into(),
+        );
+    }
+    msgs
 }
 
 // Update an ask in orderbook storage.
@@ -393,9 +1170,9 @@ fn update_ask_order(storage: &mut dyn Storage, order: AskOrder) -> Result<(), Co
     let mut book = ask_orders(storage);
     // Persist ask order
     if order.is_closed() {
-        book.remove(&key);
+        book.remove(key);
     } else {
-        book.save(&key, &order)?;
+        book.save(key, &order)?;
     }
     Ok(())
 }
@@ -407,28 +1184,52 @@ fn update_bid_order(storage: &mut dyn Storage, order: BidOrder) -> Result<(), Co
     let mut book = bid_orders(storage);
     // Persist bid order
     if order.is_closed() {
-        book.remove(&key);
+        book.remove(key);
     } else {
-        book.save(&key, &order)?;
+        book.save(key, &order)?;
     }
     Ok(())
 }
 
 /// Query does nothing
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> Result<QueryResponse, ContractError> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> Result<QueryResponse, ContractError> {
     match msg {
-        QueryMsg::GetBidOrders {} => try_get_bid_orders(deps),
-        QueryMsg::GetAskOrders {} => try_get_ask_orders(deps),
+        QueryMsg::GetBidOrders { start_after, limit } => {
+            try_get_bid_orders(deps, start_after, limit)
+        }
+        QueryMsg::GetAskOrders { start_after, limit } => {
+            try_get_ask_orders(deps, start_after, limit)
+        }
         QueryMsg::GetOrderbook {} => try_get_orderbook(deps),
+        QueryMsg::GetOrderEscrow { id } => try_get_order_escrow(deps, id),
+        QueryMsg::GetMatchablePairsCount {} => try_get_matchable_pairs_count(deps, env),
+        QueryMsg::FindOrder { id } => try_find_order(deps, id),
+        QueryMsg::GetSpreadBps {} => try_get_spread_bps(deps),
+        QueryMsg::GetOrderAge { id } => try_get_order_age(deps, env, id),
+        QueryMsg::GetUnmatchableOrders {} => try_get_unmatchable_orders(deps),
+        QueryMsg::GetDepth { limit } => try_get_depth(deps, limit),
+        QueryMsg::GetOrdersByAddress { address } => try_get_orders_by_address(deps, address),
+        QueryMsg::SimulateMatch {} => try_simulate_match(deps, env),
+        QueryMsg::GetImbalance {} => try_get_imbalance(deps),
+        QueryMsg::GetMarketStats {} => try_get_market_stats(deps),
+        QueryMsg::GetConfig {} => try_get_config(deps),
     }
 }
 
-// Read all bid orders into memory, sort by price/ts, then serialize to JSON.
-fn try_get_bid_orders(deps: Deps) -> Result<QueryResponse, ContractError> {
-    // Query sorted bid orders, checking for errors
-    let bid_orders = get_bid_orders(deps)?;
+// Read a page of bid orders, sort by price/ts, then serialize to JSON.
+fn try_get_bid_orders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<QueryResponse, ContractError> {
+    // Query a sorted page of bid orders, checking for errors
+    let mut bid_orders = get_orders_page(bid_orders_read(deps.storage), start_after, limit)?;
+    sort_bid_orders(&mut bid_orders);
     // Serialize and return
-    let bin = to_binary(&BidOrders { bid_orders })?;
+    let bin = to_binary(&BidOrders {
+        bid_orders,
+        denom_aliases: config_read(deps.storage).load()?.denom_aliases,
+    })?;
     Ok(bin)
 }
 
@@ -446,25 +1247,38 @@ fn get_bid_orders(deps: Deps) -> Result<Vec<BidOrder>, ContractError> {
     // Check for error
     let mut bid_orders = bid_orders?;
 
-    // Sort by price, then time.
+    // Return sorted in price-time order
+    sort_bid_orders(&mut bid_orders);
+    Ok(bid_orders)
+}
+
+// Sort bid orders by market, price, then time.
+fn sort_bid_orders(bid_orders: &mut [BidOrder]) {
     bid_orders.sort_by(|a, b| {
-        if a.price != b.price {
+        if a.market != b.market {
+            b.market.cmp(&a.market) // market orders take the best price
+        } else if a.price != b.price {
             b.price.cmp(&a.price) // flip comparison for best price first
         } else {
             a.ts.cmp(&b.ts)
         }
     });
-
-    // Return sorted in price-time order
-    Ok(bid_orders)
 }
 
-// Read all ask orders into memory, sort by amount/ts, then serialize to JSON.
-fn try_get_ask_orders(deps: Deps) -> Result<QueryResponse, ContractError> {
-    // Query sorted ask orders, checking for errors
-    let ask_orders = get_ask_orders(deps)?;
+// Read a page of ask orders, sort by price/ts, then serialize to JSON.
+fn try_get_ask_orders(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> Result<QueryResponse, ContractError> {
+    // Query a sorted page of ask orders, checking for errors
+    let mut ask_orders = get_orders_page(ask_orders_read(deps.storage), start_after, limit)?;
+    sort_ask_orders(&mut ask_orders);
     // Serialize and return
-    let bin = to_binary(&AskOrders { ask_orders })?;
+    let bin = to_binary(&AskOrders {
+        ask_orders,
+        denom_aliases: config_read(deps.storage).load()?.denom_aliases,
+    })?;
     Ok(bin)
 }
 
@@ -482,17 +1296,22 @@ fn get_ask_orders(deps: Deps) -> Result<Vec<AskOrder>, ContractError> {
     // Check for error
     let mut ask_orders = ask_orders?;
 
-    // Sort by price, then time.
+    // Return sorted in price-time order
+    sort_ask_orders(&mut ask_orders);
+    Ok(ask_orders)
+}
+
+// Sort ask orders by market, price, then time.
+fn sort_ask_orders(ask_orders: &mut [AskOrder]) {
     ask_orders.sort_by(|a, b| {
-        if a.price != b.price {
+        if a.market != b.market {
+            b.market.cmp(&a.market) // market orders take the best price
+        } else if a.price != b.price {
             b.price.cmp(&a.price) // flip comparison for best price first
         } else {
             a.ts.cmp(&b.ts)
         }
     });
-
-    // Return sorted in price-time order
-    Ok(ask_orders)
 }
 
 // Read all ask orders into memory, sort by price/ts, then serialize to JSON.
@@ -505,131 +1324,3980 @@ fn try_get_orderbook(deps: Deps) -> Result<QueryResponse, ContractError> {
     let bin = to_binary(&Orderbook {
         bid_orders,
         ask_orders,
+        denom_aliases: config_read(deps.storage).load()?.denom_aliases,
     })?;
     Ok(bin)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{from_binary, Addr, Api};
-    use provwasm_mocks::mock_dependencies;
+// Query the sorted bid and ask orders placed by an address.
+fn try_get_orders_by_address(deps: Deps, address: String) -> Result<QueryResponse, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+    let bid_orders: Vec<BidOrder> = get_bid_orders(deps)?
+        .into_iter()
+        .filter(|bid| bid.bidder == address)
+        .collect();
+    let ask_orders: Vec<AskOrder> = get_ask_orders(deps)?
+        .into_iter()
+        .filter(|ask| ask.asker == address)
+        .collect();
+    // Serialize and return
+    let bin = to_binary(&Orderbook {
+        bid_orders,
+        ask_orders,
+        denom_aliases: config_read(deps.storage).load()?.denom_aliases,
+    })?;
+    Ok(bin)
+}
 
-    #[test]
-    fn valid_init() {
-        // Create mock deps.
-        let mut deps = mock_dependencies(&[]);
+// Look up the funds still escrowed for an order, checking bids before asks.
+fn try_get_order_escrow(deps: Deps, id: String) -> Result<QueryResponse, ContractError> {
+    let key = id.as_bytes();
+    let escrow = if let Some(bid) = bid_orders_read(deps.storage).may_load(key)? {
+        OrderEscrow {
+            id,
+            side: OrderSide::Bid,
+            funds: bid.funds,
+            funds_denom: bid.funds_denom,
+        }
+    } else if let Some(ask) = ask_orders_read(deps.storage).may_load(key)? {
+        OrderEscrow {
+            id,
+            side: OrderSide::Ask,
+            funds: ask.funds,
+            funds_denom: ask.funds_denom,
+        }
+    } else {
+        return Err(ContractError::OrderNotFound { id });
+    };
+    // Serialize and return
+    let bin = to_binary(&escrow)?;
+    Ok(bin)
+}
 
-        // Init
-        let res = instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("admin", &[]),
-            InitMsg {
-                bid_denom: "stablecoin".into(),
-            },
-        )
-        .unwrap();
+// Look up an order by id in either book, without requiring the caller to know its side.
+fn try_find_order(deps: Deps, id: String) -> Result<QueryResponse, ContractError> {
+    let key = id.as_bytes();
+    let found = if let Some(bid) = bid_orders_read(deps.storage).may_load(key)? {
+        FoundOrder::Bid(bid)
+    } else if let Some(ask) = ask_orders_read(deps.storage).may_load(key)? {
+        FoundOrder::Ask(ask)
+    } else {
+        FoundOrder::NotFound
+    };
+    // Serialize and return
+    let bin = to_binary(&found)?;
+    Ok(bin)
+}
 
-        // Ensure no messages were created.
-        assert_eq!(0, res.messages.len());
+// Report the seconds elapsed since a bid or ask order was placed.
+fn try_get_order_age(deps: Deps, env: Env, id: String) -> Result<QueryResponse, ContractError> {
+    let key = id.as_bytes();
+    let order_ts = if let Some(bid) = bid_orders_read(deps.storage).may_load(key)? {
+        bid.ts
+    } else if let Some(ask) = ask_orders_read(deps.storage).may_load(key)? {
+        ask.ts
+    } else {
+        return Err(ContractError::OrderNotFound { id });
+    };
+    let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    let age = ts.saturating_sub(order_ts);
+    // Serialize and return
+    let bin = to_binary(&OrderAge { id, age })?;
+    Ok(bin)
+}
 
-        // Read state
-        let config_state = config_read(&deps.storage).load().unwrap();
+// Compute the spread between the best ask and best bid as basis points of the mid price.
+fn try_get_spread_bps(deps: Deps) -> Result<QueryResponse, ContractError> {
+    let spread_bps = match (best_bid_price(deps)?, best_ask_price(deps)?) {
+        (Some(bid), Some(ask)) => {
+            let (bid, ask) = (bid.numerator() as i128, ask.numerator() as i128);
+            // (ask - bid) / ((ask + bid) / 2) * 10_000
+            Some(((ask - bid) * 20_000 / (ask + bid)) as i64)
+        }
+        _ => None,
+    };
+    // Serialize and return
+    let bin = to_binary(&SpreadBps { spread_bps })?;
+    Ok(bin)
+}
 
-        // Ensure expected state values
-        assert_eq!(config_state.ask_denom, "nhash");
-        assert_eq!(config_state.ask_increment, Uint128(1_000_000_000));
-        assert_eq!(config_state.bid_denom, "stablecoin");
-    }
+// Find the highest limit bid price, if any limit bids exist.
+fn best_bid_price(deps: Deps) -> Result<Option<Decimal>, ContractError> {
+    let bids = get_bid_orders(deps)?;
+    Ok(bids
+        .iter()
+        .filter(|bid| !bid.market)
+        .map(|bid| bid.price)
+        .max())
+}
 
-    #[test]
-    fn persist_bid_order() {
-        // Create mock deps.
-        let mut deps = mock_dependencies(&[]);
+// Find the lowest limit ask price, if any limit asks exist.
+fn best_ask_price(deps: Deps) -> Result<Option<Decimal>, ContractError> {
+    let asks = get_ask_orders(deps)?;
+    Ok(asks
+        .iter()
+        .filter(|ask| !ask.market)
+        .map(|ask| ask.price)
+        .min())
+}
 
-        // Init
-        instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("admin", &[]),
-            InitMsg {
-                bid_denom: "stablecoin".into(),
-            },
-        )
-        .unwrap();
+// Count the crossing bid/ask pairs a match would consider, without simulating fills.
+fn try_get_matchable_pairs_count(deps: Deps, env: Env) -> Result<QueryResponse, ContractError> {
+    // Load config state
+    let state = config_read(deps.storage).load()?;
+    let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
 
-        // Buy 10 hash at 1 stablecoin/hash price
-        let funds = coin(10, "stablecoin");
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("bidder", &[funds]),
-            ExecuteMsg::Bid {
-                id: "test-bid".into(),
-                price: Uint128(1),
-            },
-        )
-        .unwrap();
+    // Apply the same filters as the match algorithm, ignoring orders in the current block and
+    // expired orders.
+    let bids: Vec<BidOrder> = get_bid_orders(deps)?
+        .into_iter()
+        .filter(|bid| bid.ts < ts && !bid.is_expired(ts))
+        .collect();
+    let count = get_ask_orders(deps)?
+        .iter()
+        .filter(|ask| ask.ts < ts && !ask.is_expired(ts))
+        .map(|ask| {
+            bids.iter()
+                .filter(|bid| crosses(bid, ask) && bid.bidder != ask.asker)
+                .filter(|bid| fills_min(bid, ask, &state))
+                .count() as u64
+        })
+        .sum();
 
-        // Query bids from orderbook
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetBidOrders {}).unwrap();
+    // Serialize and return
+    let bin = to_binary(&MatchablePairsCount { count })?;
+    Ok(bin)
+}
 
-        // Ensure bid side of orderbook has the expected state
-        let rep: BidOrders = from_binary(&bin).unwrap();
-        deps.api.debug(&format!("{:?}", rep));
-        assert_eq!(rep.bid_orders.len(), 1);
-        assert_eq!(rep.bid_orders[0].id, "test-bid");
-        assert_eq!(rep.bid_orders[0].price, Uint128(1));
-        assert_eq!(rep.bid_orders[0].funds, Uint128(10));
-        assert_eq!(rep.bid_orders[0].proceeds, Uint128(10_000_000_000));
-    }
+// Report the fills a match would make in the current block, using the same pairing as a match but
+// discarding the bank sends and leaving the orders untouched.
+fn try_simulate_match(deps: Deps, env: Env) -> Result<QueryResponse, ContractError> {
+    let state = config_read(deps.storage).load()?;
+    let fills = pair_orders(deps, &env, &state, None, None, &mut Response::new())?
+        .into_iter()
+        .map(|fill| SimulatedFill {
+            bid_id: fill.bid.id,
+            ask_id: fill.ask.id,
+            fill_price: fill.fill_price,
+            nhash_moved: fill.fill_ask_amount,
+            stablecoin_moved: fill.fill_bid_amount,
+        })
+        .collect();
+    let bin = to_binary(&SimulatedMatch { fills })?;
+    Ok(bin)
+}
 
-    #[test]
-    fn persist_ask_order() {
-        // Create mock deps.
-        let mut deps = mock_dependencies(&[]);
+// Query the total funds and order count at each price, best price first. Market orders have no
+// price, so they're left out.
+fn try_get_depth(deps: Deps, limit: Option<u32>) -> Result<QueryResponse, ContractError> {
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+
+    // Bids are sorted by highest price first.
+    let mut bids = price_levels(
+        get_bid_orders(deps)?
+            .iter()
+            .filter(|bid| !bid.market)
+            .map(|bid| (bid.price, bid.funds)),
+    );
+    bids.truncate(limit);
+
+    // Asks are sorted by highest price first too, so reverse them to put the best price first.
+    let mut asks = price_levels(
+        get_ask_orders(deps)?
+            .iter()
+            .filter(|ask| !ask.market)
+            .map(|ask| (ask.price, ask.funds)),
+    );
+    asks.reverse();
+    asks.truncate(limit);
 
-        // Init
-        instantiate(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("admin", &[]),
-            InitMsg {
-                bid_denom: "stablecoin".into(),
-            },
-        )
-        .unwrap();
+    // Serialize and return
+    let bin = to_binary(&Depth { bids, asks })?;
+    Ok(bin)
+}
 
-        // Sell 10 hash at 1 stablecoin/hash price
-        let funds = coin(10_000_000_000, "nhash");
-        execute(
-            deps.as_mut(),
-            mock_env(),
-            mock_info("asker", &[funds]),
-            ExecuteMsg::Ask {
-                id: "test-ask".into(),
-                price: Uint128(1),
-            },
-        )
-        .unwrap();
+// Query the total ask denom wanted by resting bids versus offered by resting asks. Volumes are
+// summed over the same price levels as the depth query, so market orders are left out.
+fn try_get_imbalance(deps: Deps) -> Result<QueryResponse, ContractError> {
+    let total = |levels: Vec<PriceLevel>| {
+        levels
+            .into_iter()
+            .fold(Uint128::zero(), |total, level| total + level.total_funds)
+    };
+    let bid_volume = total(price_levels(
+        get_bid_orders(deps)?
+            .iter()
+            .filter(|bid| !bid.market)
+            .map(|bid| (bid.price, bid.proceeds)),
+    ));
+    let ask_volume = total(price_levels(
+        get_ask_orders(deps)?
+            .iter()
+            .filter(|ask| !ask.market)
+            .map(|ask| (ask.price, ask.funds)),
+    ));
+    let ratio = if ask_volume.is_zero() {
+        None
+    } else {
+        Some(Decimal::from_ratio(bid_volume, ask_volume))
+    };
+    let bin = to_binary(&Imbalance {
+        bid_volume,
+        ask_volume,
+        ratio,
+    })?;
+    Ok(bin)
+}
 
-        // Query asks from orderbook
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetAskOrders {}).unwrap();
+// Query the config state.
+fn try_get_config(deps: Deps) -> Result<QueryResponse, ContractError> {
+    let state = config_read(deps.storage).load()?;
+    let bin = to_binary(&state)?;
+    Ok(bin)
+}
 
-        // Ensure bid side of orderbook has the expected state
-        let rep: AskOrders = from_binary(&bin).unwrap();
-        deps.api.debug(&format!("{:?}", rep));
-        assert_eq!(rep.ask_orders.len(), 1);
+// Query the cumulative matched volume, which is all zeros before the first match.
+fn try_get_market_stats(deps: Deps) -> Result<QueryResponse, ContractError> {
+    let stats = market_stats_read(deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let bin = to_binary(&stats)?;
+    Ok(bin)
+}
+
+// Group (price, funds) pairs sorted by price into price levels.
+fn price_levels(orders: impl Iterator<Item = (Decimal, Uint128)>) -> Vec<PriceLevel> {
+    let mut levels: Vec<PriceLevel> = Vec::new();
+    for (price, funds) in orders {
+        match levels.last_mut() {
+            Some(level) if level.price == price => {
+                level.total_funds += funds;
+                level.order_count += 1;
+            }
+            _ => levels.push(PriceLevel {
+                price,
+                total_funds: funds,
+                order_count: 1,
+            }),
+        }
+    }
+    levels
+}
+
+// Determine whether a crossing bid and ask would fill at least the min fill when matched.
+fn fills_min(bid: &BidOrder, ask: &AskOrder, state: &State) -> bool {
+    match price_market_bid(bid.clone(), ask, state) {
+        Some((bid, _)) => match price_market_ask(ask.clone(), &bid, state) {
+            Some(ask) => bid.proceeds.min(ask.funds) >= state.min_fill,
+            None => false,
+        },
+        None => false,
+    }
+}
+
+// Query orders that can't match anything at the current book state, with the reason why.
+fn try_get_unmatchable_orders(deps: Deps) -> Result<QueryResponse, ContractError> {
+    // Load config state and the full book
+    let state = config_read(deps.storage).load()?;
+    let bids = get_bid_orders(deps)?;
+    let asks = get_ask_orders(deps)?;
+
+    // Check each order against every order on the other side of the book
+    let mut orders: Vec<UnmatchableOrder> = Vec::new();
+    for bid in bids.iter() {
+        let crossing: Vec<&AskOrder> = asks.iter().filter(|ask| crosses(bid, ask)).collect();
+        let reason = unmatchable_reason(
+            asks.is_empty(),
+            crossing
+                .iter()
+                .map(|ask| (bid.bidder == ask.asker, fills_min(bid, ask, &state))),
+        );
+        if let Some(reason) = reason {
+            orders.push(UnmatchableOrder {
+                id: bid.id.clone(),
+                side: OrderSide::Bid,
+                reason,
+            });
+        }
+    }
+    for ask in asks.iter() {
+        let crossing: Vec<&BidOrder> = bids.iter().filter(|bid| crosses(bid, ask)).collect();
+        let reason = unmatchable_reason(
+            bids.is_empty(),
+            crossing
+                .iter()
+                .map(|bid| (bid.bidder == ask.asker, fills_min(bid, ask, &state))),
+        );
+        if let Some(reason) = reason {
+            orders.push(UnmatchableOrder {
+                id: ask.id.clone(),
+                side: OrderSide::Ask,
+                reason,
+            });
+        }
+    }
+
+    // Serialize and return
+    let bin = to_binary(&UnmatchableOrders { orders })?;
+    Ok(bin)
+}
+
+// Determine why an order can't match, given whether the other side of the book is empty and a
+// (self trade, fills min) flag pair for each crossing order. Returns None if the order can match.
+fn unmatchable_reason(
+    counter_empty: bool,
+    crossing: impl Iterator<Item = (bool, bool)>,
+) -> Option<UnmatchableReason> {
+    if counter_empty {
+        return Some(UnmatchableReason::NoCounterOrders);
+    }
+    let mut reason = UnmatchableReason::PriceDoesNotCross;
+    for (self_trade, fills_min) in crossing {
+        if self_trade {
+            if reason == UnmatchableReason::PriceDoesNotCross {
+                reason = UnmatchableReason::SelfTradeOnly;
+            }
+        } else if fills_min {
+            return None;
+        } else {
+            reason = UnmatchableReason::BelowMinFill;
+        }
+    }
+    Some(reason)
+}
+
+// Ensure the total number of open bids and asks is below the configured max, if any.
+fn ensure_book_not_full(storage: &dyn Storage, state: &State) -> Result<(), ContractError> {
+    if let Some(max_open_orders) = state.max_open_orders {
+        let bids = bid_orders_read(storage)
+            .range(None, None, Order::Ascending)
+            .count();
+        let asks = ask_orders_read(storage)
+            .range(None, None, Order::Ascending)
+            .count();
+        if (bids + asks) as u64 >= max_open_orders {
+            return Err(ContractError::BookFull {});
+        }
+    }
+    Ok(())
+}
+
+// Ensure an order expires after the current block, since orders can't match until the next one.
+fn validate_expiration(env: &Env, expires_at: Option<u64>) -> Result<(), ContractError> {
+    let ts = env.block.time.nanos() / 1_000_000_000; // use seconds
+    if expires_at.is_some_and(|expires_at| expires_at <= ts) {
+        return Err(ContractError::InvalidExpiration {
+            message: "expiration must be after the current block time".into(),
+        });
+    }
+    Ok(())
+}
+
+// Ensure an order id is non-empty, not too long, and only uses alphanumerics, '-', '_' or '.'.
+fn validate_order_id(id: &str) -> Result<(), ContractError> {
+    if id.is_empty() {
+        return Err(ContractError::InvalidOrderId {
+            message: "order id must not be empty".into(),
+        });
+    }
+    if id.len() > MAX_ORDER_ID_LEN {
+        return Err(ContractError::InvalidOrderId {
+            message: format!("order id must be <= {} characters", MAX_ORDER_ID_LEN),
+        });
+    }
+    if !id
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+    {
+        return Err(ContractError::InvalidOrderId {
+            message: "order id contains invalid characters".into(),
+        });
+    }
+    Ok(())
+}
+
+/// Called when migrating a contract instance to a new code ID.
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Load config state for the denoms required by upgraded orders, upgrading config persisted
+    // before fee collectors were added. This also ensures the stored config deserializes cleanly
+    // before anything is migrated.
+    let state = match config_read(deps.storage).load() {
+        Ok(state) => state,
+        Err(_) => {
+            let state = legacy_config_read(deps.storage).load()?.upgrade();
+            config(deps.storage).save(&state)?;
+            state
+        }
+    };
+
+    // Rewrite any orders persisted under an older schema. Orders placed before block heights were
+    // tracked get UNKNOWN_HEIGHT.
+    let bids = migrate_bid_orders(deps.storage, &state)?;
+    let asks = migrate_ask_orders(deps.storage, &state)?;
+
+    // Report the number of upgraded orders
+    let mut res = Response::new();
+    res.add_attribute("action", "orderbook.migrate");
+    res.add_attribute("bids_upgraded", bids.to_string());
+    res.add_attribute("asks_upgraded", asks.to_string());
+
+    // Rescale resting orders if the ask increment is changing, refunding any dust.
+    if let Some(ask_increment) = msg.ask_increment {
+        if ask_increment.is_zero() {
+            return Err(ContractError::InvalidFunds {
+                message: "ask increment must be > 0".into(),
+            });
+        }
+        for msg in rescale_bid_orders(deps.storage, state.ask_increment, ask_increment)? {
+            res.add_message(msg);
+        }
+        for msg in rescale_ask_orders(deps.storage, state.ask_increment, ask_increment)? {
+            res.add_message(msg);
+        }
+        config(deps.storage).update(|mut state| -> Result<_, ContractError> {
+            state.ask_increment = ask_increment;
+            Ok(state)
+        })?;
+        res.add_attribute("ask_increment", ask_increment);
+    }
+
+    // Report any drift between the escrow open orders require and the contract balance. Funds
+    // are not moved; drift must be corrected manually.
+    if msg.reconcile.unwrap_or(false) {
+        let mut drift = false;
+        for (denom, expected) in expected_escrow(deps.as_ref())? {
+            let actual = deps
+                .querier
+                .query_balance(env.contract.address.clone(), &denom)?
+                .amount;
+            drift |= actual != expected;
+            res.add_attribute(
+                format!("reconcile.{}", denom),
+                format!("expected:{},actual:{}", expected, actual),
+            );
+        }
+        res.add_attribute("reconcile.drift", drift.to_string());
+    }
+
+    Ok(res)
+}
+
+// Sum the funds held for all open orders by denom.
+fn expected_escrow(deps: Deps) -> Result<BTreeMap<String, Uint128>, ContractError> {
+    let mut escrow: BTreeMap<String, Uint128> = BTreeMap::new();
+    for bid in get_bid_orders(deps)? {
+        *escrow.entry(bid.funds_denom).or_default() += bid.funds;
+    }
+    for ask in get_ask_orders(deps)? {
+        *escrow.entry(ask.funds_denom).or_default() += ask.funds;
+    }
+    Ok(escrow)
+}
+
+// Convert a price per old ask increment to a price per new ask increment, so an order keeps the
+// same price per unit of the ask denom.
+fn rescale_price(price: Decimal, old_increment: Uint128, ask_increment: Uint128) -> Decimal {
+    let atomics = Uint128(price.numerator())
+        .multiply_ratio(ask_increment, old_increment)
+        .u128();
+    let fractional = price.denominator();
+    Decimal::from_ratio(atomics / fractional, 1u128)
+        + Decimal::from_ratio(atomics % fractional, fractional)
+}
+
+// Recompute bid prices and proceeds for a new ask increment. Prices are per increment, so they're
+// rescaled to keep the same price per hash, and a bid can only keep funds for the whole increments
+// it can afford. The rest is refunded.
+fn rescale_bid_orders(
+    storage: &mut dyn Storage,
+    old_increment: Uint128,
+    ask_increment: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let mut msgs: Vec<CosmosMsg> = Vec::new();
+    for mut order in get_orders(bid_orders_read(storage))? {
+        // Market bids have no price; their proceeds are calculated when matched.
+        if order.market {
+            continue;
+        }
+        order.price = rescale_price(order.price, old_increment, ask_increment);
+        let units = units_at_price(order.funds, order.price);
+        let funds = Uint128(units) * order.price;
+        let refund = order.funds.u128() - funds.u128();
+        if refund > 0 {
+            msgs.push(
+                BankMsg::Send {
+                    amount: vec![coin(refund, order.funds_denom.clone())],
+                    to_address: order.bidder.to_string(),
+                }
+                .into(),
+            );
+        }
+        order.funds = funds;
+        order.proceeds = Uint128(units * ask_increment.u128());
+        update_bid_order(storage, order)?;
+    }
+    Ok(msgs)
+}
+
+// Recompute ask prices and proceeds for a new ask increment. Prices are rescaled to keep the same
+// price per hash, and asks can only keep funds in whole increments. The rest is refunded. Asks
+// whose proceeds would no longer be a whole amount are refunded in full and closed.
+fn rescale_ask_orders(
+    storage: &mut dyn Storage,
+    old_increment: Uint128,
+    ask_increment: Uint128,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let state = State {
+        ask_increment,
+        ..config_read(storage).load()?
+    };
+    let mut msgs: Vec<CosmosMsg> = Vec::new();
+    for mut order in get_orders(ask_orders_read(storage))? {
+        order.price = rescale_price(order.price, old_increment, ask_increment);
+        let units = order.funds.u128() / ask_increment.u128();
+        let (funds, proceeds) = match ask_proceeds(order.funds, order.price, &state) {
+            Ok(proceeds) => (Uint128(units * ask_increment.u128()), proceeds),
+            Err(_) => (Uint128::zero(), Uint128::zero()),
+        };
+        let refund = order.funds.u128() - funds.u128();
+        if refund > 0 {
+            msgs.push(
+                BankMsg::Send {
+                    amount: vec![coin(refund, order.funds_denom.clone())],
+                    to_address: order.asker.to_string(),
+                }
+                .into(),
+            );
+        }
+        order.funds = funds;
+        order.proceeds = proceeds;
+        update_ask_order(storage, order)?;
+    }
+    Ok(msgs)
+}
+
+// Read a page of orders from a bucket in storage key order, starting after the given order id.
+fn get_orders_page<T>(
+    book: ReadonlyBucket<T>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+    // Range starts are inclusive, so skip past the given id by appending a zero byte.
+    let start = start_after.map(|id| {
+        let mut key = id.into_bytes();
+        key.push(0);
+        key
+    });
+    book.range(start.as_deref(), None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, order)| order))
+        .collect()
+}
+
+// Read all orders from a bucket in storage key order.
+fn get_orders<T>(book: ReadonlyBucket<T>) -> StdResult<Vec<T>>
+where
+    T: Serialize + DeserializeOwned,
+{
+    book.range(None, None, Order::Ascending)
+        .map(|item| item.map(|(_, order)| order))
+        .collect()
+}
+
+// Upgrade bid orders that no longer deserialize under the current schema. The legacy schema
+// reads both old and new orders, since unknown fields are ignored and its price is a decimal.
+fn migrate_bid_orders(storage: &mut dyn Storage, state: &State) -> Result<u32, ContractError> {
+    let orders: StdResult<Vec<_>> = legacy_bid_orders_read(storage)
+        .range(None, None, Order::Ascending)
+        .collect();
+    let mut upgraded = 0;
+    for (key, order) in orders? {
+        if bid_orders_read(storage).load(&key).is_err() {
+            bid_orders(storage).save(&key, &order.upgrade(&state.ask_denom))?;
+            upgraded += 1;
+        }
+    }
+    Ok(upgraded)
+}
+
+// Upgrade ask orders that no longer deserialize under the current schema. The legacy schema
+// reads both old and new orders, since unknown fields are ignored and its price is a decimal.
+fn migrate_ask_orders(storage: &mut dyn Storage, state: &State) -> Result<u32, ContractError> {
+    let orders: StdResult<Vec<_>> = legacy_ask_orders_read(storage)
+        .range(None, None, Order::Ascending)
+        .collect();
+    let mut upgraded = 0;
+    for (key, order) in orders? {
+        if ask_orders_read(storage).load(&key).is_err() {
+            ask_orders(storage).save(&key, &order.upgrade(&state.bid_denom))?;
+            upgraded += 1;
+        }
+    }
+    Ok(upgraded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::{
+        legacy_ask_orders, legacy_bid_orders, DenomAlias, LegacyAskOrder, LegacyBidOrder,
+        MarketStats, UNKNOWN_HEIGHT,
+    };
+    use cosmwasm_std::testing::{mock_env, mock_info};
+    use cosmwasm_std::{from_binary, Addr, Api, Coin};
+    use provwasm_mocks::mock_dependencies;
+
+    #[test]
+    fn valid_init() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        let res = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure no messages were created.
+        assert_eq!(0, res.messages.len());
+
+        // Read state
+        let config_state = config_read(&deps.storage).load().unwrap();
+
+        // Ensure expected state values
+        assert_eq!(config_state.ask_denom, "nhash");
+        assert_eq!(config_state.ask_increment, Uint128(1_000_000_000));
+        assert_eq!(config_state.bid_denom, "stablecoin");
+        assert_eq!(config_state.min_fill, Uint128::zero());
+    }
+
+    #[test]
+    fn custom_ask_init() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with a non-hash market
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ask_denom: Some("gold".into()),
+                ask_increment: Some(Uint128(100)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure expected state values
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.ask_denom, "gold");
+        assert_eq!(config_state.ask_increment, Uint128(100));
+    }
+
+    #[test]
+    fn invalid_ask_init() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with a zero ask increment
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ask_increment: Some(Uint128::zero()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InvalidFunds { message } => {
+                assert_eq!(message, "ask increment must be > 0")
+            }
+            _ => panic!("unexpected error type"),
+        }
+
+        // Init with the same bid and ask denom
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ask_denom: Some("stablecoin".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InvalidFunds { message } => {
+                assert_eq!(message, "ask denom must not be the bid denom")
+            }
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn persist_bid_order() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query bids from orderbook
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure bid side of orderbook has the expected state
+        let rep: BidOrders = from_binary(&bin).unwrap();
+        deps.api.debug(&format!("{:?}", rep));
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.bid_orders[0].id, "test-bid");
+        assert_eq!(rep.bid_orders[0].price, Decimal::from_ratio(1u128, 1u128));
+        assert_eq!(rep.bid_orders[0].funds, Uint128(10));
+        assert_eq!(rep.bid_orders[0].proceeds, Uint128(10_000_000_000));
+    }
+
+    #[test]
+    fn persist_ask_order() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query asks from orderbook
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAskOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure bid side of orderbook has the expected state
+        let rep: AskOrders = from_binary(&bin).unwrap();
+        deps.api.debug(&format!("{:?}", rep));
+        assert_eq!(rep.ask_orders.len(), 1);
+        assert_eq!(rep.ask_orders[0].id, "test-ask");
+        assert_eq!(rep.ask_orders[0].price, Decimal::from_ratio(1u128, 1u128));
+        assert_eq!(rep.ask_orders[0].funds, Uint128(10_000_000_000));
+        assert_eq!(rep.ask_orders[0].proceeds, Uint128(10));
+    }
+
+    #[test]
+    fn direct_match() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query the orderbook
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+
+        // Ensure both orders were added to the orderbook.
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+
+        deps.api.debug(&format!("{:?}", rep));
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]), // Admin must execute match
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure we got two bank sends
+        assert_eq!(res.messages.len(), 2);
+
+        // Ensure the fill details were reported
+        let attr = |key: &str| {
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+        };
+        assert_eq!(attr("orderbook.fill_price"), Some("1".into()));
+        assert_eq!(
+            attr("orderbook.fill_ask_amount"),
+            Some("10000000000".into())
+        );
+        assert_eq!(attr("orderbook.fill_bid_amount"), Some("10".into()));
+
+        // Ensure we got the expected bank transfer amounts.
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(amount.len(), 1);
+                if to_address == Addr::unchecked("asker") {
+                    let expected_asker_amount = coin(10, "stablecoin");
+                    assert_eq!(amount[0], expected_asker_amount);
+                } else {
+                    assert_eq!(to_address, "bidder");
+                    let expected_bidder_amount = coin(10_000_000_000, "nhash");
+                    assert_eq!(amount[0], expected_bidder_amount);
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Ensure we got one match event attribute, followed by the fill details
+        assert_eq!(res.attributes.len(), 4);
+        assert_eq!(res.attributes[0].key, "orderbook.match");
+        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+
+        // Ensure both orders were removed from the orderbook.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 0);
+        assert_eq!(rep.ask_orders.len(), 0);
+    }
+
+    #[test]
+    fn partial_match_bid() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 5 hash at 1 stablecoin/hash price
+        let funds = coin(5_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query the orderbook
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+
+        // Ensure both orders were added to the orderbook.
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+
+        deps.api.debug(&format!("{:?}", rep));
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]), // Admin must execute match
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure we got two bank sends
+        assert_eq!(res.messages.len(), 2);
+
+        // Ensure we got the expected bank transfer amounts.
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(amount.len(), 1);
+                if to_address == Addr::unchecked("asker") {
+                    let expected_amount = coin(5, "stablecoin");
+                    assert_eq!(amount[0], expected_amount);
+                } else {
+                    assert_eq!(to_address, "bidder");
+                    let expected_amount = coin(5_000_000_000, "nhash");
+                    assert_eq!(amount[0], expected_amount);
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Ensure we got one match event attribute, followed by the fill details
+        assert_eq!(res.attributes.len(), 4);
+        assert_eq!(res.attributes[0].key, "orderbook.match");
+        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+
+        // Ensure the bid order was updated in the orderbook.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 0);
+
+        // Verfiy there are still 5 hash proceeds in the bid order
+        assert_eq!(rep.bid_orders[0].id, "test-bid");
+        assert_eq!(rep.bid_orders[0].price, Decimal::from_ratio(1u128, 1u128));
+        assert_eq!(rep.bid_orders[0].funds, Uint128(5));
+        assert_eq!(rep.bid_orders[0].proceeds, Uint128(5_000_000_000));
+    }
+
+    #[test]
+    fn partial_match_ask() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 5 hash at 1 stablecoin/hash price
+        let funds = coin(5, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query the orderbook
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+
+        // Ensure both orders were added to the orderbook.
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+
+        deps.api.debug(&format!("{:?}", rep));
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]), // Admin must execute match
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure we got two bank sends
+        assert_eq!(res.messages.len(), 2);
+
+        // Ensure we got the expected bank transfer amounts.
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(amount.len(), 1);
+                if to_address == Addr::unchecked("asker") {
+                    let expected_amount = coin(5, "stablecoin");
+                    assert_eq!(amount[0], expected_amount);
+                } else {
+                    assert_eq!(to_address, "bidder");
+                    let expected_amount = coin(5_000_000_000, "nhash");
+                    assert_eq!(amount[0], expected_amount);
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Ensure we got one match event attribute, followed by the fill details
+        assert_eq!(res.attributes.len(), 4);
+        assert_eq!(res.attributes[0].key, "orderbook.match");
+        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+
+        // Ensure the ask order was updated in the orderbook.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 0);
+        assert_eq!(rep.ask_orders.len(), 1);
+
+        // Verify there are still 5 stablecoin proceeds in the ask order
         assert_eq!(rep.ask_orders[0].id, "test-ask");
-        assert_eq!(rep.ask_orders[0].price, Uint128(1));
-        assert_eq!(rep.ask_orders[0].funds, Uint128(10_000_000_000));
-        assert_eq!(rep.ask_orders[0].proceeds, Uint128(10));
+        assert_eq!(rep.ask_orders[0].price, Decimal::from_ratio(1u128, 1u128));
+        assert_eq!(rep.ask_orders[0].funds, Uint128(5_000_000_000));
+        assert_eq!(rep.ask_orders[0].proceeds, Uint128(5));
+    }
+
+    #[test]
+    fn unauthorized_bid() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 5 hash at 1 stablecoin/hash price
+        let funds = coin(5, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[funds]), // Admin cannot place bid orders
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn unauthorized_ask() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[funds]), // Admin cannot place ask orders
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn unauthorized_match() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Execute a match
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[]), // Admin must execute match
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn invalid_bid_amount() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Attempt to buy < 1hash at 15 stablecoin/hash price
+        let funds = coin(1, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(15u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::InvalidFunds { message } => {
+                assert_eq!(
+                    message,
+                    "bid funds must buy at least one increment at the bid price"
+                )
+            }
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn fractional_price() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Bid 4 stablecoin at 1.5 stablecoin/hash, which buys 2 hash
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(4, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "bid".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].price, Decimal::percent(150));
+        assert_eq!(rep.bid_orders[0].proceeds, Uint128(2_000_000_000));
+
+        // Ask 2 hash at the same price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(2_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "ask".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // The asker gets the proceeds, and the bidder gets the hash plus leftover funds.
+        assert_eq!(
+            res.messages,
+            vec![
+                CosmosMsg::Bank(BankMsg::Send {
+                    amount: vec![coin(3, "stablecoin")],
+                    to_address: "asker".into(),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    amount: vec![coin(2_000_000_000, "nhash")],
+                    to_address: "bidder".into(),
+                }),
+                CosmosMsg::Bank(BankMsg::Send {
+                    amount: vec![coin(1, "stablecoin")],
+                    to_address: "bidder".into(),
+                }),
+            ]
+        );
+
+        // Ensure the fill reports what the asker was paid, not the refunded funds
+        let attr = |key: &str| {
+            res.attributes
+                .iter()
+                .find(|a| a.key == key)
+                .map(|a| a.value.clone())
+        };
+        assert_eq!(attr("orderbook.fill_price"), Some("1.5".into()));
+        assert_eq!(attr("orderbook.fill_bid_amount"), Some("3".into()));
+        assert_eq!(attr("orderbook.fill_ask_amount"), Some("2000000000".into()));
+
+        // Ensure both orders were closed
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn invalid_ask_amount() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Attempt to sell < 1 hash at 1 stablecoin/hash price
+        let funds = coin(123456789, "nhash");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::InvalidFunds { message } => assert_eq!(
+                message,
+                "ask amount must be > 0 in the required increments: got 123456789"
+            ),
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn invalid_ask_proceeds() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Attempt to sell 3 hash at 1.5 stablecoin/hash price yielding fractional proceeds
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(3_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we go the expected error
+        match err {
+            ContractError::InvalidFunds { message } => {
+                assert_eq!(message, "ask price must yield an integral for proceeds")
+            }
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn migrate_legacy_orders() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Seed orders persisted under the old schema
+        let legacy_bid = LegacyBidOrder {
+            id: "legacy-bid".into(),
+            price: Decimal::from_ratio(2u128, 1u128),
+            ts: 123,
+            bidder: Addr::unchecked("bidder"),
+            funds: Uint128(10),
+            funds_denom: "stablecoin".into(),
+            proceeds: Uint128(5_000_000_000),
+        };
+        legacy_bid_orders(&mut deps.storage)
+            .save(b"legacy-bid", &legacy_bid)
+            .unwrap();
+        let legacy_ask = LegacyAskOrder {
+            id: "legacy-ask".into(),
+            price: Decimal::from_ratio(1u128, 1u128),
+            ts: 456,
+            asker: Addr::unchecked("asker"),
+            funds: Uint128(3_000_000_000),
+            funds_denom: "nhash".into(),
+            proceeds: Uint128(3),
+        };
+        legacy_ask_orders(&mut deps.storage)
+            .save(b"legacy-ask", &legacy_ask)
+            .unwrap();
+
+        // Add an order under the current schema
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "current-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure stale orders can't be read before migration
+        assert!(query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).is_err());
+
+        // Migrate
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+        assert_eq!(res.attributes[1].value, "1");
+        assert_eq!(res.attributes[2].value, "1");
+
+        // Ensure the stale orders were upgraded without losing data
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 2);
+        assert_eq!(rep.ask_orders.len(), 1);
+        assert_eq!(rep.bid_orders[0], legacy_bid.upgrade("nhash"));
+        assert_eq!(rep.bid_orders[0].proceeds_denom, "nhash");
+        assert_eq!(rep.bid_orders[0].height, UNKNOWN_HEIGHT);
+        assert_eq!(rep.bid_orders[1].id, "current-bid");
+        assert_eq!(rep.bid_orders[1].height, mock_env().block.height);
+        assert_eq!(rep.ask_orders[0], legacy_ask.upgrade("stablecoin"));
+        assert_eq!(rep.ask_orders[0].proceeds_denom, "stablecoin");
+        assert_eq!(rep.ask_orders[0].height, UNKNOWN_HEIGHT);
+    }
+
+    #[test]
+    fn migrate_fractional_price_orders() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Seed an order under the old schema, and rest a bid with a fractional price
+        let legacy_ask = LegacyAskOrder {
+            id: "legacy-ask".into(),
+            price: Decimal::from_ratio(2u128, 1u128),
+            ts: 456,
+            asker: Addr::unchecked("asker"),
+            funds: Uint128(1_000_000_000),
+            funds_denom: "nhash".into(),
+            proceeds: Uint128(2),
+        };
+        legacy_ask_orders(&mut deps.storage)
+            .save(b"legacy-ask", &legacy_ask)
+            .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(3, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "fractional-bid".into(),
+                price: Decimal::from_ratio(3u128, 2u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        let bid = bid_orders_read(&deps.storage)
+            .load(b"fractional-bid")
+            .unwrap();
+
+        // Migrate
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+        assert_eq!(res.attributes[1].value, "0");
+        assert_eq!(res.attributes[2].value, "1");
+
+        // Ensure the fractional bid was left unchanged and the stale ask was upgraded
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders, vec![bid]);
+        assert_eq!(rep.ask_orders, vec![legacy_ask.upgrade("stablecoin")]);
+    }
+
+    #[test]
+    fn query_order_escrow() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 5 hash at 1 stablecoin/hash price
+        let funds = coin(5_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match, partially filling the bid
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the remaining bid escrow is reported
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOrderEscrow {
+                id: "test-bid".into(),
+            },
+        )
+        .unwrap();
+        let rep: OrderEscrow = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            OrderEscrow {
+                id: "test-bid".into(),
+                side: OrderSide::Bid,
+                funds: Uint128(5),
+                funds_denom: "stablecoin".into(),
+            }
+        );
+
+        // Ensure closed orders are not found
+        let err = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOrderEscrow {
+                id: "test-ask".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderNotFound { id } => assert_eq!(id, "test-ask"),
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn skip_dust_match() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init, requiring matches to fill at least 5 hash
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                min_fill: Some(Uint128(5_000_000_000)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 1 hash at 1 stablecoin/hash price
+        let funds = coin(1_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the crossing orders were not matched
+        assert_eq!(res.messages.len(), 0);
+        assert_eq!(res.attributes.len(), 0);
+
+        // Ensure both orders are still resting, unchanged.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+        assert_eq!(rep.bid_orders[0].funds, Uint128(10));
+        assert_eq!(rep.ask_orders[0].funds, Uint128(1_000_000_000));
+    }
+
+    #[test]
+    fn query_matchable_pairs_count() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 2 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask-1".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Query as if in the next block
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Ensure no pairs cross
+        let bin = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetMatchablePairsCount {},
+        )
+        .unwrap();
+        let rep: MatchablePairsCount = from_binary(&bin).unwrap();
+        assert_eq!(rep.count, 0);
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask-2".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure the crossing pair is counted
+        let bin = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetMatchablePairsCount {},
+        )
+        .unwrap();
+        let rep: MatchablePairsCount = from_binary(&bin).unwrap();
+        assert_eq!(rep.count, 1);
+
+        // Sell 10 hash at 1 stablecoin/hash price, expiring in 5 seconds
+        let now = mock_env().block.time.nanos() / 1_000_000_000;
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask-3".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: Some(now + 5),
+            },
+        )
+        .unwrap();
+
+        // Ensure the expiring pair is counted until the ask expires
+        let bin = query(deps.as_ref(), env, QueryMsg::GetMatchablePairsCount {}).unwrap();
+        let rep: MatchablePairsCount = from_binary(&bin).unwrap();
+        assert_eq!(rep.count, 2);
+        let mut expired_env = mock_env();
+        expired_env.block.time = expired_env.block.time.plus_seconds(10);
+        let bin = query(
+            deps.as_ref(),
+            expired_env,
+            QueryMsg::GetMatchablePairsCount {},
+        )
+        .unwrap();
+        let rep: MatchablePairsCount = from_binary(&bin).unwrap();
+        assert_eq!(rep.count, 1);
+
+        // Ensure orders in the current block are not counted
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetMatchablePairsCount {},
+        )
+        .unwrap();
+        let rep: MatchablePairsCount = from_binary(&bin).unwrap();
+        assert_eq!(rep.count, 0);
+    }
+
+    #[test]
+    fn migrate_ask_increment() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 2 hash at 5 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(5u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 3 hash at 7 stablecoin/hash price
+        let funds = coin(3_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(7u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Migrate to a 2 hash increment
+        let res = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                ask_increment: Some(Uint128(2_000_000_000)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure the hash that no longer fits the increment was refunded to the asker
+        assert_eq!(res.messages.len(), 1);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "asker");
+                assert_eq!(amount, &vec![coin(1_000_000_000, "nhash")]);
+            }
+            _ => panic!("unexpected message type"),
+        }
+
+        // Ensure config state was updated
+        let config_state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(config_state.ask_increment, Uint128(2_000_000_000));
+
+        // Ensure prices are per 2 hash, with the same stablecoin per hash as before, and proceeds
+        // were recomputed from them.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].price, Decimal::from_ratio(10u128, 1u128));
+        assert_eq!(rep.bid_orders[0].funds, Uint128(10));
+        assert_eq!(rep.bid_orders[0].proceeds, Uint128(2_000_000_000));
+        assert_eq!(rep.ask_orders[0].price, Decimal::from_ratio(14u128, 1u128));
+        assert_eq!(rep.ask_orders[0].funds, Uint128(2_000_000_000));
+        assert_eq!(rep.ask_orders[0].proceeds, Uint128(14));
+    }
+
+    #[test]
+    fn migrate_ask_increment_fractional_proceeds() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Sell 4 hash at 0.25 stablecoin/hash price
+        let funds = coin(4_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 4u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Migrate to a 3 hash increment, where the ask could only sell 3 hash for 0.75 stablecoin
+        let res = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                ask_increment: Some(Uint128(3_000_000_000)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure the ask was refunded in full and closed rather than shorting the asker
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                amount: vec![coin(4_000_000_000, "nhash")],
+                to_address: "asker".into(),
+            })]
+        );
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn find_order() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure the bid is found without specifying the side
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FindOrder {
+                id: "test-bid".into(),
+            },
+        )
+        .unwrap();
+        match from_binary(&bin).unwrap() {
+            FoundOrder::Bid(bid) => {
+                assert_eq!(bid.id, "test-bid");
+                assert_eq!(bid.bidder, Addr::unchecked("bidder"));
+                assert_eq!(bid.funds, Uint128(10));
+            }
+            _ => panic!("expected bid order"),
+        }
+
+        // Ensure unknown ids are reported as not found
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::FindOrder {
+                id: "unknown".into(),
+            },
+        )
+        .unwrap();
+        let rep: FoundOrder = from_binary(&bin).unwrap();
+        assert_eq!(rep, FoundOrder::NotFound);
+    }
+
+    #[test]
+    fn invalid_order_id() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Bid with an id that exceeds the max length
+        let funds = coin(10, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "x".repeat(MAX_ORDER_ID_LEN + 1),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InvalidOrderId { .. } => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Ask with an empty id
+        let funds = coin(1_000_000_000, "nhash");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::InvalidOrderId { .. } => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn query_spread_bps() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure no spread is reported for an empty book
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetSpreadBps {}).unwrap();
+        let rep: SpreadBps = from_binary(&bin).unwrap();
+        assert_eq!(rep.spread_bps, None);
+
+        // Buy 1 hash at 99 stablecoin/hash price
+        let funds = coin(99, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(99u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 1 hash at 101 stablecoin/hash price
+        let funds = coin(1_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(101u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure the spread of 2 is reported as 200 bps of the mid price of 100
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetSpreadBps {}).unwrap();
+        let rep: SpreadBps = from_binary(&bin).unwrap();
+        assert_eq!(rep.spread_bps, Some(200));
+    }
+
+    #[test]
+    fn book_full() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with room for two open orders
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                max_open_orders: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Fill the book with a bid and an ask
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        let funds = coin(1_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Try to add another bid
+        let funds = coin(10, "stablecoin");
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid-2".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+
+        // Ensure we got the expected error
+        match err {
+            ContractError::BookFull {} => {}
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn max_match_iterations() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init, allowing matches to consider at most 2 bid/ask pairs
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                max_iterations: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place 3 bids, each buying 1 hash at 1 stablecoin/hash price
+        for id in ["bid-1", "bid-2", "bid-3"] {
+            let funds = coin(1, "stablecoin");
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bidder", &[funds]),
+                ExecuteMsg::Bid {
+                    id: id.into(),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // Sell 3 hash at 1 stablecoin/hash price
+        let funds = coin(3_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match, which should stop without reverting after two pairs
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the cap was reported and only two bids were filled
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "orderbook.max_iterations_reached"));
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders[0].funds, Uint128(1_000_000_000));
+
+        // Match again to fill the remaining bid
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the orderbook is now empty
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn migrate_reconcile_escrow() {
+        // Create mock deps, where the contract holds less stablecoin than open bids require.
+        let mut deps = mock_dependencies(&[coin(4, "stablecoin")]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Migrate, reconciling escrow
+        let res = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                reconcile: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Ensure the drift was reported without moving any funds
+        assert!(res.messages.is_empty());
+        let attr = |key: &str| {
+            res.attributes
+                .iter()
+                .find(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+        };
+        assert_eq!(
+            attr("reconcile.stablecoin"),
+            Some("expected:10,actual:4".into())
+        );
+        assert_eq!(attr("reconcile.drift"), Some("true".into()));
+    }
+
+    #[test]
+    fn cancel_bid() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        let funds = coin(10, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 4 hash at 1 stablecoin/hash price
+        let funds = coin(4_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match, partially filling the bid
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure an unknown bid can't be cancelled
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[]),
+            ExecuteMsg::CancelBid {
+                id: "unknown".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderNotFound { id } => assert_eq!(id, "unknown"),
+            _ => panic!("unexpected error type"),
+        }
+
+        // Ensure only the bidder can cancel
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[]),
+            ExecuteMsg::CancelBid {
+                id: "test-bid".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Cancel the bid
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[]),
+            ExecuteMsg::CancelBid {
+                id: "test-bid".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure only the unmatched funds were refunded
+        assert_eq!(res.messages.len(), 1);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "bidder");
+                assert_eq!(amount, &vec![coin(6, "stablecoin")]);
+            }
+            _ => panic!("unexpected message type"),
+        }
+
+        // Ensure the bid was removed from the orderbook
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let rep: BidOrders = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+    }
+
+    #[test]
+    fn cancel_ask() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 4 hash at 1 stablecoin/hash price
+        let funds = coin(4, "stablecoin");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[funds]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let funds = coin(10_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match, partially filling the ask
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Get the funds remaining on the ask
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetAskOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let rep: AskOrders = from_binary(&bin).unwrap();
+        let remaining = rep.ask_orders[0].funds;
+        assert_eq!(remaining, Uint128(6_000_000_000));
+
+        // Ensure only the asker can cancel
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[]),
+            ExecuteMsg::CancelAsk {
+                id: "test-ask".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error type"),
+        }
+
+        // Cancel the ask
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[]),
+            ExecuteMsg::CancelAsk {
+                id: "test-ask".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure only the remaining funds were returned
+        assert_eq!(res.messages.len(), 1);
+        match &res.messages[0] {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                assert_eq!(to_address, "asker");
+                assert_eq!(amount, &vec![coin(remaining.u128(), "nhash")]);
+            }
+            _ => panic!("unexpected message type"),
+        }
+
+        // Ensure the ask can't be cancelled again
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[]),
+            ExecuteMsg::CancelAsk {
+                id: "test-ask".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderNotFound { id } => assert_eq!(id, "test-ask"),
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn query_order_age() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Sell 1 hash at 1 stablecoin/hash price
+        let funds = coin(1_000_000_000, "nhash");
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[funds]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(90);
+
+        // Ensure the age of the ask is reported
+        let bin = query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetOrderAge {
+                id: "test-ask".into(),
+            },
+        )
+        .unwrap();
+        let rep: OrderAge = from_binary(&bin).unwrap();
+        assert_eq!(rep.age, 90);
+
+        // Ensure unknown orders are reported as not found
+        let err = query(
+            deps.as_ref(),
+            env,
+            QueryMsg::GetOrderAge {
+                id: "unknown".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderNotFound { id } => assert_eq!(id, "unknown"),
+            _ => panic!("unexpected error type"),
+        }
+    }
+
+    #[test]
+    fn paginate_bid_orders() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place 50 bids, each buying 1 hash at a different price
+        for i in 0..50 {
+            let price = (i % 7) + 1;
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("bidder", &[coin(price, "stablecoin")]),
+                ExecuteMsg::Bid {
+                    id: format!("bid-{:02}", i),
+                    price: Decimal::from_ratio(price, 1u128),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // Ensure the default page size is used when no limit is given
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let rep: BidOrders = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 30);
+
+        // Fetch the first page
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: None,
+                limit: Some(25),
+            },
+        )
+        .unwrap();
+        let page1: BidOrders = from_binary(&bin).unwrap();
+        assert_eq!(page1.bid_orders.len(), 25);
+
+        // Ensure the page is in price-time priority
+        assert!(page1
+            .bid_orders
+            .windows(2)
+            .all(|w| w[0].price >= w[1].price));
+
+        // Fetch the second page, starting after the last id (by key order) in the first page
+        let last_id = page1.bid_orders.iter().map(|b| b.id.clone()).max();
+        assert_eq!(last_id, Some("bid-24".into()));
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: last_id,
+                limit: Some(25),
+            },
+        )
+        .unwrap();
+        let page2: BidOrders = from_binary(&bin).unwrap();
+        assert_eq!(page2.bid_orders.len(), 25);
+
+        // Ensure the pages don't overlap and cover every order
+        let mut ids: Vec<String> = page1
+            .bid_orders
+            .iter()
+            .chain(page2.bid_orders.iter())
+            .map(|b| b.id.clone())
+            .collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 50);
+    }
+
+    #[test]
+    fn market_bid() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Market bids are rejected when there are no asks to price them against
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(7, "stablecoin")]),
+            ExecuteMsg::MarketBid {
+                id: "market-bid".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::NoCounterOrders {} => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        // Sell 3 hash at 2 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(3_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Buy as much hash as 7 stablecoin will get at the best ask price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(7, "stablecoin")]),
+            ExecuteMsg::MarketBid {
+                id: "market-bid".into(),
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the asker was paid, the bidder got hash, and the left over stablecoin was refunded.
+        let sends: Vec<(String, Coin)> = res
+            .messages
+            .into_iter()
+            .map(|msg| match msg {
+                CosmosMsg::Bank(BankMsg::Send {
+                    amount, to_address, ..
+                }) => (to_address, amount[0].clone()),
+                _ => panic!("unexpected message type"),
+            })
+            .collect();
+        assert_eq!(
+            sends,
+            vec![
+                ("asker".to_string(), coin(6, "stablecoin")),
+                ("bidder".to_string(), coin(3_000_000_000, "nhash")),
+                ("bidder".to_string(), coin(1, "stablecoin")),
+            ]
+        );
+
+        // Ensure both orders were filled and removed
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn market_ask() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 2 hash at 5 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(5u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 3 hash at the best bid price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(3_000_000_000, "nhash")]),
+            ExecuteMsg::MarketAsk {
+                id: "market-ask".into(),
+            },
+        )
+        .unwrap();
+
+        // Market orders don't affect the spread
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetSpreadBps {}).unwrap();
+        let rep: SpreadBps = from_binary(&bin).unwrap();
+        assert_eq!(rep.spread_bps, None);
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the bid was filled at the bid price
+        assert_eq!(res.messages.len(), 2);
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                if to_address == "asker" {
+                    assert_eq!(amount[0], coin(10, "stablecoin"));
+                } else {
+                    assert_eq!(to_address, "bidder");
+                    assert_eq!(amount[0], coin(2_000_000_000, "nhash"));
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Ensure the rest of the market ask is still resting
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert_eq!(rep.ask_orders.len(), 1);
+        assert!(rep.ask_orders[0].market);
+        assert_eq!(rep.ask_orders[0].funds, Uint128(1_000_000_000));
+    }
+
+    #[test]
+    fn market_ask_fractional_proceeds() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 2 hash at 0.5 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(1, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 2u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 1 hash at the best bid price, which would yield half a stablecoin
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(1_000_000_000, "nhash")]),
+            ExecuteMsg::MarketAsk {
+                id: "market-ask".into(),
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the pair was skipped rather than sending the asker nothing for their hash
+        assert!(res.messages.is_empty());
+        assert!(!res.attributes.iter().any(|a| a.key == "orderbook.match"));
+
+        // Ensure both orders are still resting
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+    }
+
+    #[test]
+    fn order_height() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place orders a few blocks apart
+        let mut env = mock_env();
+        env.block.height = 100;
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        env.block.height = 105;
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("asker", &[coin(10_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Ensure each order carries the height it was placed at
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].height, 100);
+        assert_eq!(rep.ask_orders[0].height, 105);
+    }
+
+    #[test]
+    fn match_with_fee() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Ensure fees over 100% are rejected
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                fee_bps: Some(10_001),
+                ..Default::default()
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InvalidFunds { .. } => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        // Init with a 1.5% fee
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                fee_bps: Some(150),
+                fee_collector: Some("collector".into()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 1001 hash at 1 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(1001, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 1001 hash at 1 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(1_001_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure fees were rounded down and sent to the collector alongside each settlement.
+        let sends: Vec<(String, Coin)> = res
+            .messages
+            .into_iter()
+            .map(|msg| match msg {
+                CosmosMsg::Bank(BankMsg::Send {
+                    amount, to_address, ..
+                }) => (to_address, amount[0].clone()),
+                _ => panic!("unexpected message type"),
+            })
+            .collect();
+        assert_eq!(
+            sends,
+            vec![
+                ("asker".to_string(), coin(986, "stablecoin")),
+                ("collector".to_string(), coin(15, "stablecoin")),
+                ("bidder".to_string(), coin(985_985_000_000, "nhash")),
+                ("collector".to_string(), coin(15_015_000_000, "nhash")),
+            ]
+        );
+    }
+
+    #[test]
+    fn entry_fee() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with a 1 stablecoin entry fee
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                fee_collector: Some("collector".into()),
+                entry_fee: Some(Uint128(1)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Bid 11 stablecoin, which pays the fee out of the bid funds
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(11, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        let fee_msg = CosmosMsg::Bank(BankMsg::Send {
+            amount: vec![coin(1, "stablecoin")],
+            to_address: "collector".into(),
+        });
+        assert_eq!(res.messages, vec![fee_msg.clone()]);
+
+        // Asks must send the fee alongside the funds to sell
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(2_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InvalidFunds { message } => {
+                assert_eq!(message, "ask entry fee must be 1stablecoin: got 0")
+            }
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                "asker",
+                &[coin(2_000_000_000, "nhash"), coin(1, "stablecoin")],
+            ),
+            ExecuteMsg::Ask {
+                id: "ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages, vec![fee_msg]);
+
+        // Ensure only the net funds are escrowed
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].funds, Uint128(10));
+        assert_eq!(rep.bid_orders[0].proceeds, Uint128(10_000_000_000));
+        assert_eq!(rep.ask_orders[0].funds, Uint128(2_000_000_000));
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOrderEscrow { id: "bid".into() },
+        )
+        .unwrap();
+        let escrow: OrderEscrow = from_binary(&bin).unwrap();
+        assert_eq!(escrow.funds, Uint128(10));
+    }
+
+    #[test]
+    fn auto_match() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with auto match enabled
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                auto_match: Some(true),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(10_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
+        // Move block time forward so the ask is from an earlier block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Place a crossing bid, which is matched right away.
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 2);
+        res.messages.into_iter().for_each(|msg| match msg {
+            CosmosMsg::Bank(BankMsg::Send {
+                amount, to_address, ..
+            }) => {
+                if to_address == "asker" {
+                    assert_eq!(amount[0], coin(10, "stablecoin"));
+                } else {
+                    assert_eq!(to_address, "bidder");
+                    assert_eq!(amount[0], coin(10_000_000_000, "nhash"));
+                }
+            }
+            _ => panic!("unexpected message type"),
+        });
+
+        // Ensure both orders were closed
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
+
+        // Place crossing orders in the same block. They can't match yet.
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("asker", &[coin(5_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "same-block-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bidder", &[coin(5, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "same-block-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        assert!(res.messages.is_empty());
+
+        // Place another order in the next block, settling the crossing orders without a match.
+        env.block.time = env.block.time.plus_seconds(3);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("bidder", &[coin(1, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "low-bid".into(),
+                price: Decimal::from_ratio(1u128, 2u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(res.messages.len(), 2);
+
+        // Ensure only the new bid is left resting
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.bid_orders[0].id, "low-bid");
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn skip_self_trade() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Buy 10 hash at 1 stablecoin/hash price
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Sell 10 hash at 1 stablecoin/hash price from the same account
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("trader", &[coin(10_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Execute a match
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the self trade was skipped and reported
+        assert!(res.messages.is_empty());
+        assert!(res
+            .attributes
+            .iter()
+            .any(|attr| attr.key == "orderbook.self_trade_skipped" && attr.value == "test-ask"));
+
+        // Ensure both orders are still resting
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.ask_orders.len(), 1);
+    }
+
+    #[test]
+    fn query_unmatchable_orders() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place a crossing bid and ask, plus a bid and ask far from the rest of the book
+        for (sender, funds, msg) in [
+            (
+                "bidder",
+                coin(10, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "test-bid".into(),
+                    price: Decimal::from_ratio(5u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "bidder",
+                coin(10, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "low-bid".into(),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "asker",
+                coin(2_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "test-ask".into(),
+                    price: Decimal::from_ratio(5u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "asker",
+                coin(2_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "high-ask".into(),
+                    price: Decimal::from_ratio(100u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+        ] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[funds]), msg).unwrap();
+        }
+
+        // Query unmatchable orders
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetUnmatchableOrders {}).unwrap();
+
+        // Ensure only the far-off orders were reported
+        let rep: UnmatchableOrders = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep.orders,
+            vec![
+                UnmatchableOrder {
+                    id: "low-bid".into(),
+                    side: OrderSide::Bid,
+                    reason: UnmatchableReason::PriceDoesNotCross,
+                },
+                UnmatchableOrder {
+                    id: "high-ask".into(),
+                    side: OrderSide::Ask,
+                    reason: UnmatchableReason::PriceDoesNotCross,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn query_depth() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place two bids at the same price and one lower, plus asks at two prices
+        for (sender, funds, msg) in [
+            (
+                "bidder",
+                coin(4, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "bid-1".into(),
+                    price: Decimal::from_ratio(2u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "bidder",
+                coin(6, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "bid-2".into(),
+                    price: Decimal::from_ratio(2u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "bidder",
+                coin(3, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "bid-3".into(),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "asker",
+                coin(2_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "ask-1".into(),
+                    price: Decimal::from_ratio(5u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "asker",
+                coin(1_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "ask-2".into(),
+                    price: Decimal::from_ratio(3u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+        ] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[funds]), msg).unwrap();
+        }
+
+        // Query the top price level on each side
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetDepth { limit: Some(1) },
+        )
+        .unwrap();
+        let rep: Depth = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            Depth {
+                bids: vec![PriceLevel {
+                    price: Decimal::from_ratio(2u128, 1u128),
+                    total_funds: Uint128(10),
+                    order_count: 2,
+                }],
+                asks: vec![PriceLevel {
+                    price: Decimal::from_ratio(3u128, 1u128),
+                    total_funds: Uint128(1_000_000_000),
+                    order_count: 1,
+                }],
+            }
+        );
+
+        // Ensure all levels are returned by default
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetDepth { limit: None },
+        )
+        .unwrap();
+        let rep: Depth = from_binary(&bin).unwrap();
+        assert_eq!(rep.bids.len(), 2);
+        assert_eq!(rep.bids[1].price, Decimal::from_ratio(1u128, 1u128));
+        assert_eq!(rep.asks.len(), 2);
+        assert_eq!(rep.asks[1].price, Decimal::from_ratio(5u128, 1u128));
+    }
+
+    #[test]
+    fn query_orders_by_address() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place a bid and an ask from one account and a bid from another
+        for (sender, funds, msg) in [
+            (
+                "trader",
+                coin(10, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "trader-bid".into(),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "trader",
+                coin(1_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "trader-ask".into(),
+                    price: Decimal::from_ratio(5u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "bidder",
+                coin(10, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "other-bid".into(),
+                    price: Decimal::from_ratio(2u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+        ] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[funds]), msg).unwrap();
+        }
+
+        // Query the first account's orders
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetOrdersByAddress {
+                address: "trader".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure only that account's orders were returned
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders.len(), 1);
+        assert_eq!(rep.bid_orders[0].id, "trader-bid");
+        assert_eq!(rep.ask_orders.len(), 1);
+        assert_eq!(rep.ask_orders[0].id, "trader-ask");
+    }
+
+    #[test]
+    fn max_matches() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place five bids for 1 hash each
+        for i in 0..5 {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info(&format!("bidder-{}", i), &[coin(1, "stablecoin")]),
+                ExecuteMsg::Bid {
+                    id: format!("bid-{}", i),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            )
+            .unwrap();
+        }
+
+        // Sell 5 hash to cross every bid
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(5_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+
+        // Move block time forward so it seems like we're matching in the next block.
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+
+        // Match two bids at a time until the book is empty
+        for (expected_matches, expected_remaining) in [(2, true), (2, true), (1, false)] {
+            let res = execute(
+                deps.as_mut(),
+                env.clone(),
+                mock_info("admin", &[]),
+                ExecuteMsg::Match {
+                    max_matches: Some(2),
+                },
+            )
+            .unwrap();
+
+            // Ensure the cap was respected, with two bank sends per match
+            assert_eq!(res.messages.len(), expected_matches * 2);
+            let remaining = res
+                .attributes
+                .iter()
+                .any(|attr| attr.key == "orderbook.matches_remaining");
+            assert_eq!(remaining, expected_remaining);
+        }
+
+        // Ensure all orders were filled across the capped matches
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
+    }
+
+    #[test]
+    fn migrate_preserves_config() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with non-default config
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                min_fill: Some(Uint128(1_000_000_000)),
+                max_open_orders: Some(10),
+                fee_bps: Some(25),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let before = config_read(&deps.storage).load().unwrap();
+
+        // Migrate
+        migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        // Ensure the config is unchanged
+        let after = config_read(&deps.storage).load().unwrap();
+        assert_eq!(before, after);
+
+        // Seed config persisted by the original contract, before any options were added
+        let key = cosmwasm_storage::to_length_prefixed(crate::state::CONFIG_KEY);
+        deps.storage.set(
+            &key,
+            br#"{"ask_denom":"nhash","ask_increment":"1000000000","bid_denom":"stablecoin","contract_admin":"admin"}"#,
+        );
+        assert!(config_read(&deps.storage).load().is_err());
+
+        // Migrate
+        migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).unwrap();
+
+        // Ensure the config was upgraded with defaults, collecting fees to the admin
+        let state = config_read(&deps.storage).load().unwrap();
+        assert_eq!(state.bid_denom, "stablecoin");
+        assert_eq!(state.ask_increment, Uint128(1_000_000_000));
+        assert_eq!(state.contract_admin, Addr::unchecked("admin"));
+        assert_eq!(state.fee_collector, Addr::unchecked("admin"));
+        assert_eq!(state.min_fill, Uint128::zero());
+        assert_eq!(state.fee_bps, 0);
+        assert!(!state.paused);
+
+        // Ensure migrate refuses to run when the config can't be read
+        deps.storage.set(&key, b"{}");
+        assert!(migrate(deps.as_mut(), mock_env(), MigrateMsg::default()).is_err());
+    }
+
+    #[test]
+    fn order_too_small() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init with min order sizes of 10 stablecoin and 2 hash
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                min_bid_funds: Some(Uint128(10)),
+                min_ask_funds: Some(Uint128(2_000_000_000)),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Bid one stablecoin below the min
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(9, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderTooSmall { min } => assert_eq!(min, Uint128(10)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        // Ask one nhash below the min, using an increment of 1 so the amount is valid otherwise.
+        config(&mut deps.storage)
+            .update(|mut state| -> StdResult<_> {
+                state.ask_increment = Uint128(1);
+                Ok(state)
+            })
+            .unwrap();
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(1_999_999_999, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::OrderTooSmall { min } => assert_eq!(min, Uint128(2_000_000_000)),
+            _ => panic!("unexpected error: {:?}", err),
+        }
+
+        // Ensure orders at the min are accepted
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
     }
 
     #[test]
-    fn direct_match() {
+    fn pause() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -640,94 +5308,391 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Buy 10 hash at 1 stablecoin/hash price
-        let funds = coin(10, "stablecoin");
+        // Place a bid before pausing
+        let bid = |id: &str| ExecuteMsg::Bid {
+            id: id.into(),
+            price: Decimal::from_ratio(1u128, 1u128),
+            expires_at: None,
+        };
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("bidder", &[funds]),
-            ExecuteMsg::Bid {
-                id: "test-bid".into(),
-                price: Uint128(1),
-            },
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            bid("bid-1"),
         )
         .unwrap();
 
-        // Sell 10 hash at 1 stablecoin/hash price
-        let funds = coin(10_000_000_000, "nhash");
+        // Ensure only the admin can pause
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[]),
+            ExecuteMsg::SetPaused { paused: true },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("asker", &[funds]),
-            ExecuteMsg::Ask {
-                id: "test-ask".into(),
-                price: Uint128(1),
-            },
+            mock_info("admin", &[]),
+            ExecuteMsg::SetPaused { paused: true },
         )
         .unwrap();
 
-        // Query the orderbook
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        // Ensure bidding and matching fail while paused
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            bid("bid-2"),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Paused {} => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Paused {} => {}
+            _ => panic!("unexpected error: {:?}", err),
+        }
 
-        // Ensure both orders were added to the orderbook.
-        let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 1);
-        assert_eq!(rep.ask_orders.len(), 1);
+        // Ensure cancels are still allowed while paused
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[]),
+            ExecuteMsg::CancelBid { id: "bid-1".into() },
+        )
+        .unwrap();
 
-        deps.api.debug(&format!("{:?}", rep));
+        // Ensure bidding succeeds after unpausing
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::SetPaused { paused: false },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            bid("bid-2"),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn price_improvement() {
+        // A bid at 2 stablecoin/hash crosses an ask at 1 stablecoin/hash. The later order gets
+        // the improvement: a later bid is refunded, and a later ask is paid the bid price.
+        for (bid_first, expected_sends) in [
+            (
+                false,
+                vec![
+                    ("asker", coin(3, "stablecoin")),
+                    ("bidder", coin(3_000_000_000, "nhash")),
+                    ("bidder", coin(3, "stablecoin")),
+                ],
+            ),
+            (
+                true,
+                vec![
+                    ("asker", coin(6, "stablecoin")),
+                    ("bidder", coin(3_000_000_000, "nhash")),
+                ],
+            ),
+        ] {
+            // Create mock deps.
+            let mut deps = mock_dependencies(&[]);
+
+            // Init
+            instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("admin", &[]),
+                InitMsg {
+                    bid_denom: "stablecoin".into(),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+            // Place the orders one second apart
+            let mut env = mock_env();
+            let mut orders = vec![
+                (
+                    mock_info("asker", &[coin(3_000_000_000, "nhash")]),
+                    ExecuteMsg::Ask {
+                        id: "test-ask".into(),
+                        price: Decimal::from_ratio(1u128, 1u128),
+                        expires_at: None,
+                    },
+                ),
+                (
+                    mock_info("bidder", &[coin(6, "stablecoin")]),
+                    ExecuteMsg::Bid {
+                        id: "test-bid".into(),
+                        price: Decimal::from_ratio(2u128, 1u128),
+                        expires_at: None,
+                    },
+                ),
+            ];
+            if bid_first {
+                orders.reverse();
+            }
+            for (info, msg) in orders {
+                execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+                env.block.time = env.block.time.plus_seconds(1);
+            }
+
+            // Execute a match in a later block
+            env.block.time = env.block.time.plus_seconds(3);
+            let res = execute(
+                deps.as_mut(),
+                env,
+                mock_info("admin", &[]),
+                ExecuteMsg::Match { max_matches: None },
+            )
+            .unwrap();
+
+            // Ensure the trade executed at the earlier order's price
+            let sends: Vec<(String, Coin)> = res
+                .messages
+                .into_iter()
+                .map(|msg| match msg {
+                    CosmosMsg::Bank(BankMsg::Send {
+                        amount, to_address, ..
+                    }) => (to_address, amount[0].clone()),
+                    _ => panic!("unexpected message type"),
+                })
+                .collect();
+            let expected_sends: Vec<(String, Coin)> = expected_sends
+                .into_iter()
+                .map(|(to, amount)| (to.to_string(), amount))
+                .collect();
+            assert_eq!(sends, expected_sends);
+
+            // Ensure both orders were closed with nothing left in escrow
+            let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+            let rep: Orderbook = from_binary(&bin).unwrap();
+            assert!(rep.bid_orders.is_empty());
+            assert!(rep.ask_orders.is_empty());
+        }
+    }
+
+    #[test]
+    fn simulate_match() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
+
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // Place a book where one bid fills against both asks
+        let orders = [
+            ("bidder-1", coin(6, "stablecoin"), "bid-1", 2u128),
+            ("bidder-2", coin(2, "stablecoin"), "bid-2", 1u128),
+            ("asker-1", coin(2_000_000_000, "nhash"), "ask-1", 1u128),
+            ("asker-2", coin(3_000_000_000, "nhash"), "ask-2", 2u128),
+        ];
+        for (sender, funds, id, price) in orders {
+            let price = Decimal::from_ratio(price, 1u128);
+            let msg = if id.starts_with("bid") {
+                ExecuteMsg::Bid {
+                    id: id.into(),
+                    price,
+                    expires_at: None,
+                }
+            } else {
+                ExecuteMsg::Ask {
+                    id: id.into(),
+                    price,
+                    expires_at: None,
+                }
+            };
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[funds]), msg).unwrap();
+        }
 
         // Move block time forward so it seems like we're matching in the next block.
         let mut env = mock_env();
         env.block.time = env.block.time.plus_seconds(3);
 
-        // Execute a match
+        // Simulate the match, ensuring the book is left untouched
+        let book = query(deps.as_ref(), env.clone(), QueryMsg::GetOrderbook {}).unwrap();
+        let bin = query(deps.as_ref(), env.clone(), QueryMsg::SimulateMatch {}).unwrap();
+        let rep: SimulatedMatch = from_binary(&bin).unwrap();
+        assert_eq!(rep.fills.len(), 2);
+        assert_eq!(
+            query(deps.as_ref(), env.clone(), QueryMsg::GetOrderbook {}).unwrap(),
+            book
+        );
+
+        // Execute the match
         let res = execute(
             deps.as_mut(),
             env,
-            mock_info("admin", &[]), // Admin must execute match
-            ExecuteMsg::Match {},
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
         )
         .unwrap();
 
-        // Ensure we got two bank sends
-        assert_eq!(res.messages.len(), 2);
+        // Ensure the simulation matches the fills that were actually settled
+        let values = |key: &str| -> Vec<String> {
+            res.attributes
+                .iter()
+                .filter(|attr| attr.key == key)
+                .map(|attr| attr.value.clone())
+                .collect()
+        };
+        let simulated =
+            |f: fn(&SimulatedFill) -> String| -> Vec<String> { rep.fills.iter().map(f).collect() };
+        assert_eq!(
+            values("orderbook.match"),
+            simulated(|fill| format!("bid:{},ask:{}", fill.bid_id, fill.ask_id))
+        );
+        assert_eq!(
+            values("orderbook.fill_price"),
+            simulated(|fill| fill.fill_price.unwrap().to_string())
+        );
+        assert_eq!(
+            values("orderbook.fill_ask_amount"),
+            simulated(|fill| fill.nhash_moved.to_string())
+        );
+        assert_eq!(
+            values("orderbook.fill_bid_amount"),
+            simulated(|fill| fill.stablecoin_moved.to_string())
+        );
+        // Buy 2 hash at 1.5 stablecoin/hash price, sending 1 stablecoin more than needed
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder-1", &[coin(4, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "fractional-bid".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker-1", &[coin(2_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "fractional-ask".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
 
-        // Ensure we got the expected bank transfer amounts.
-        res.messages.into_iter().for_each(|msg| match msg {
-            CosmosMsg::Bank(BankMsg::Send {
-                amount, to_address, ..
-            }) => {
-                assert_eq!(amount.len(), 1);
-                if to_address == Addr::unchecked("asker") {
-                    let expected_asker_amount = coin(10, "stablecoin");
-                    assert_eq!(amount[0], expected_asker_amount);
-                } else {
-                    assert_eq!(to_address, "bidder");
-                    let expected_bidder_amount = coin(10_000_000_000, "nhash");
-                    assert_eq!(amount[0], expected_bidder_amount);
-                }
-            }
-            _ => panic!("unexpected message type"),
-        });
+        // Ensure the simulated fill counts only the stablecoin paid to the asker
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+        let bin = query(deps.as_ref(), env, QueryMsg::SimulateMatch {}).unwrap();
+        let rep: SimulatedMatch = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep.fills,
+            vec![SimulatedFill {
+                bid_id: "fractional-bid".into(),
+                ask_id: "fractional-ask".into(),
+                fill_price: Some(Decimal::percent(150)),
+                nhash_moved: Uint128(2_000_000_000),
+                stablecoin_moved: Uint128(3),
+            }]
+        );
+    }
 
-        // Ensure we got one match event attribute
-        assert_eq!(res.attributes.len(), 1);
-        assert_eq!(res.attributes[0].key, "orderbook.match");
-        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+    #[test]
+    fn query_imbalance() {
+        // Create mock deps.
+        let mut deps = mock_dependencies(&[]);
 
-        // Ensure both orders were removed from the orderbook.
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
-        let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 0);
-        assert_eq!(rep.ask_orders.len(), 0);
+        // Init
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            InitMsg {
+                bid_denom: "stablecoin".into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        // With no asks there's no ratio
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetImbalance {}).unwrap();
+        let rep: Imbalance = from_binary(&bin).unwrap();
+        assert_eq!(rep.ratio, None);
+
+        // Bid for 5 hash across two prices, and ask 2 hash above them
+        for (sender, funds, msg) in [
+            (
+                "bidder",
+                coin(4, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "bid-1".into(),
+                    price: Decimal::from_ratio(2u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "bidder",
+                coin(3, "stablecoin"),
+                ExecuteMsg::Bid {
+                    id: "bid-2".into(),
+                    price: Decimal::from_ratio(1u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+            (
+                "asker",
+                coin(2_000_000_000, "nhash"),
+                ExecuteMsg::Ask {
+                    id: "ask-1".into(),
+                    price: Decimal::from_ratio(5u128, 1u128),
+                    expires_at: None,
+                },
+            ),
+        ] {
+            execute(deps.as_mut(), mock_env(), mock_info(sender, &[funds]), msg).unwrap();
+        }
+
+        // Ensure the volumes are in hash wanted and offered, and bids outweigh asks 5:2
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetImbalance {}).unwrap();
+        let rep: Imbalance = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_volume, Uint128(5_000_000_000));
+        assert_eq!(rep.ask_volume, Uint128(2_000_000_000));
+        assert_eq!(rep.ratio, Some(Decimal::percent(250)));
     }
 
     #[test]
-    fn partial_match_bid() {
+    fn amend_order() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -738,100 +5703,134 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Buy 10 hash at 1 stablecoin/hash price
-        let funds = coin(10, "stablecoin");
+        // Place a bid at 2 stablecoin/hash, then a bid at 1 stablecoin/hash a second later
+        let env_at = |secs: u64| {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(secs);
+            env
+        };
         execute(
             deps.as_mut(),
-            mock_env(),
-            mock_info("bidder", &[funds]),
+            env_at(0),
+            mock_info("bidder-1", &[coin(2, "stablecoin")]),
             ExecuteMsg::Bid {
-                id: "test-bid".into(),
-                price: Uint128(1),
+                id: "bid-1".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
-
-        // Sell 5 hash at 1 stablecoin/hash price
-        let funds = coin(5_000_000_000, "nhash");
         execute(
             deps.as_mut(),
-            mock_env(),
-            mock_info("asker", &[funds]),
-            ExecuteMsg::Ask {
-                id: "test-ask".into(),
-                price: Uint128(1),
+            env_at(1),
+            mock_info("bidder-2", &[coin(1, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "bid-2".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
 
-        // Query the orderbook
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
-
-        // Ensure both orders were added to the orderbook.
-        let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 1);
-        assert_eq!(rep.ask_orders.len(), 1);
-
-        deps.api.debug(&format!("{:?}", rep));
+        // Only the bidder can amend their bid
+        let amend = |new_price: u128| ExecuteMsg::AmendBid {
+            id: "bid-1".into(),
+            new_price: Decimal::from_ratio(new_price, 1u128),
+        };
+        let err = execute(
+            deps.as_mut(),
+            env_at(2),
+            mock_info("bidder-2", &[]),
+            amend(1),
+        )
+        .unwrap_err();
+        assert!(matches!(err, ContractError::Unauthorized {}));
 
-        // Move block time forward so it seems like we're matching in the next block.
-        let mut env = mock_env();
-        env.block.time = env.block.time.plus_seconds(3);
+        // Amendments that can't buy a single increment are rejected
+        let err = execute(
+            deps.as_mut(),
+            env_at(2),
+            mock_info("bidder-1", &[]),
+            amend(3),
+        )
+        .unwrap_err();
+        match err {
+            ContractError::InvalidFunds { message } => assert_eq!(
+                message,
+                "bid funds must buy at least one increment at the bid price"
+            ),
+            _ => panic!("unexpected error type"),
+        }
 
-        // Execute a match
-        let res = execute(
+        // Lower the first bid to the second bid's price
+        execute(
             deps.as_mut(),
-            env,
-            mock_info("admin", &[]), // Admin must execute match
-            ExecuteMsg::Match {},
+            env_at(2),
+            mock_info("bidder-1", &[]),
+            amend(1),
         )
         .unwrap();
 
-        // Ensure we got two bank sends
-        assert_eq!(res.messages.len(), 2);
-
-        // Ensure we got the expected bank transfer amounts.
-        res.messages.into_iter().for_each(|msg| match msg {
-            CosmosMsg::Bank(BankMsg::Send {
-                amount, to_address, ..
-            }) => {
-                assert_eq!(amount.len(), 1);
-                if to_address == Addr::unchecked("asker") {
-                    let expected_amount = coin(5, "stablecoin");
-                    assert_eq!(amount[0], expected_amount);
-                } else {
-                    assert_eq!(to_address, "bidder");
-                    let expected_amount = coin(5_000_000_000, "nhash");
-                    assert_eq!(amount[0], expected_amount);
-                }
-            }
-            _ => panic!("unexpected message type"),
-        });
+        // Ensure the proceeds were recalculated and the bid moved behind the second bid
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].id, "bid-2");
+        assert_eq!(rep.bid_orders[1].id, "bid-1");
+        assert_eq!(rep.bid_orders[1].proceeds, Uint128(2_000_000_000));
+        assert_eq!(rep.bid_orders[1].ts, rep.bid_orders[0].ts + 1);
 
-        // Ensure we got one match event attribute
-        assert_eq!(res.attributes.len(), 1);
-        assert_eq!(res.attributes[0].key, "orderbook.match");
-        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+        // Sell 1 hash above the bids, then lower the ask to their price
+        execute(
+            deps.as_mut(),
+            env_at(2),
+            mock_info("asker", &[coin(1_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "ask-1".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            env_at(3),
+            mock_info("asker", &[]),
+            ExecuteMsg::AmendAsk {
+                id: "ask-1".into(),
+                new_price: Decimal::from_ratio(1u128, 1u128),
+            },
+        )
+        .unwrap();
 
-        // Ensure the bid order was updated in the orderbook.
+        // Ensure the ask proceeds were recalculated
         let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
         let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 1);
-        assert_eq!(rep.ask_orders.len(), 0);
+        assert_eq!(rep.ask_orders[0].proceeds, Uint128(1));
 
-        // Verfiy there are still 5 hash proceeds in the bid order
-        assert_eq!(rep.bid_orders[0].id, "test-bid");
-        assert_eq!(rep.bid_orders[0].price, Uint128(1));
-        assert_eq!(rep.bid_orders[0].funds, Uint128(5));
-        assert_eq!(rep.bid_orders[0].proceeds, Uint128(5_000_000_000));
+        // Ensure the match goes to the bid that kept its time priority
+        let res = execute(
+            deps.as_mut(),
+            env_at(6),
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+        let matched: Vec<_> = res
+            .attributes
+            .iter()
+            .filter(|attr| attr.key == "orderbook.match")
+            .map(|attr| attr.value.clone())
+            .collect();
+        assert_eq!(matched, vec!["bid:bid-2,ask:ask-1"]);
     }
 
     #[test]
-    fn partial_match_ask() {
+    fn market_stats() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -842,136 +5841,176 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Buy 5 hash at 1 stablecoin/hash price
-        let funds = coin(5, "stablecoin");
+        // Ensure the stats start at zero
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetMarketStats {}).unwrap();
+        let rep: MarketStats = from_binary(&bin).unwrap();
+        assert_eq!(rep, MarketStats::default());
+
+        // Buy and sell 10 hash at 1 stablecoin/hash price
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("bidder", &[funds]),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
             ExecuteMsg::Bid {
                 id: "test-bid".into(),
-                price: Uint128(1),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
-
-        // Sell 10 hash at 1 stablecoin/hash price
-        let funds = coin(10_000_000_000, "nhash");
         execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("asker", &[funds]),
+            mock_info("asker", &[coin(10_000_000_000, "nhash")]),
             ExecuteMsg::Ask {
                 id: "test-ask".into(),
-                price: Uint128(1),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
 
-        // Query the orderbook
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
-
-        // Ensure both orders were added to the orderbook.
-        let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 1);
-        assert_eq!(rep.ask_orders.len(), 1);
-
-        deps.api.debug(&format!("{:?}", rep));
-
         // Move block time forward so it seems like we're matching in the next block.
         let mut env = mock_env();
         env.block.time = env.block.time.plus_seconds(3);
-
-        // Execute a match
-        let res = execute(
+        execute(
             deps.as_mut(),
             env,
-            mock_info("admin", &[]), // Admin must execute match
-            ExecuteMsg::Match {},
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
         )
         .unwrap();
 
-        // Ensure we got two bank sends
-        assert_eq!(res.messages.len(), 2);
-
-        // Ensure we got the expected bank transfer amounts.
-        res.messages.into_iter().for_each(|msg| match msg {
-            CosmosMsg::Bank(BankMsg::Send {
-                amount, to_address, ..
-            }) => {
-                assert_eq!(amount.len(), 1);
-                if to_address == Addr::unchecked("asker") {
-                    let expected_amount = coin(5, "stablecoin");
-                    assert_eq!(amount[0], expected_amount);
-                } else {
-                    assert_eq!(to_address, "bidder");
-                    let expected_amount = coin(5_000_000_000, "nhash");
-                    assert_eq!(amount[0], expected_amount);
-                }
+        // Ensure the stats reflect the single match
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetMarketStats {}).unwrap();
+        let rep: MarketStats = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            MarketStats {
+                total_nhash_matched: Uint128(10_000_000_000),
+                total_stablecoin_matched: Uint128(10),
+                match_count: 1,
             }
-            _ => panic!("unexpected message type"),
-        });
-
-        // Ensure we got one match event attribute
-        assert_eq!(res.attributes.len(), 1);
-        assert_eq!(res.attributes[0].key, "orderbook.match");
-        assert_eq!(res.attributes[0].value, "bid:test-bid,ask:test-ask");
+        );
 
-        // Ensure the ask order was updated in the orderbook.
-        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
-        let rep: Orderbook = from_binary(&bin).unwrap();
-        assert_eq!(rep.bid_orders.len(), 0);
-        assert_eq!(rep.ask_orders.len(), 1);
+        // Buy 2 hash at 1.5 stablecoin/hash price, sending 1 stablecoin more than needed
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("bidder", &[coin(4, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "fractional-bid".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(2_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "fractional-ask".into(),
+                price: Decimal::percent(150),
+                expires_at: None,
+            },
+        )
+        .unwrap();
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(3);
+        execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
 
-        // Verify there are still 5 stablecoin proceeds in the ask order
-        assert_eq!(rep.ask_orders[0].id, "test-ask");
-        assert_eq!(rep.ask_orders[0].price, Uint128(1));
-        assert_eq!(rep.ask_orders[0].funds, Uint128(5_000_000_000));
-        assert_eq!(rep.ask_orders[0].proceeds, Uint128(5));
+        // Ensure only the 3 stablecoin paid to the asker was counted, not the refund
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetMarketStats {}).unwrap();
+        let rep: MarketStats = from_binary(&bin).unwrap();
+        assert_eq!(
+            rep,
+            MarketStats {
+                total_nhash_matched: Uint128(12_000_000_000),
+                total_stablecoin_matched: Uint128(13),
+                match_count: 2,
+            }
+        );
     }
 
     #[test]
-    fn unauthorized_bid() {
+    fn denom_aliases() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
-        // Init
+        // Init with display names for both denoms
+        let aliases = vec![
+            DenomAlias {
+                denom: "nhash".into(),
+                alias: "HASH".into(),
+            },
+            DenomAlias {
+                denom: "stablecoin".into(),
+                alias: "USD".into(),
+            },
+        ];
         instantiate(
             deps.as_mut(),
             mock_env(),
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                denom_aliases: Some(aliases.clone()),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Buy 5 hash at 1 stablecoin/hash price
-        let funds = coin(5, "stablecoin");
-        let err = execute(
+        // Ensure the aliases round-trip through the config
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetConfig {}).unwrap();
+        let rep: State = from_binary(&bin).unwrap();
+        assert_eq!(rep.denom_aliases, aliases);
+
+        // Place a bid
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("admin", &[funds]), // Admin cannot place bid orders
+            mock_info("bidder", &[coin(1, "stablecoin")]),
             ExecuteMsg::Bid {
                 id: "test-bid".into(),
-                price: Uint128(1),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
-        .unwrap_err();
+        .unwrap();
 
-        // Ensure we go the expected error
-        match err {
-            ContractError::Unauthorized {} => {}
-            _ => panic!("unexpected error type"),
-        }
+        // Ensure order queries include the aliases alongside the raw denoms
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert_eq!(rep.bid_orders[0].funds_denom, "stablecoin");
+        assert_eq!(rep.denom_aliases, aliases);
+
+        let bin = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::GetBidOrders {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+        let rep: BidOrders = from_binary(&bin).unwrap();
+        assert_eq!(rep.denom_aliases, aliases);
     }
 
     #[test]
-    fn unauthorized_ask() {
+    fn order_expiration() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -982,64 +6021,86 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Sell 10 hash at 1 stablecoin/hash price
-        let funds = coin(10_000_000_000, "nhash");
+        // Orders must expire after the current block
+        let now = mock_env().block.time.nanos() / 1_000_000_000;
+        let bid = |expires_at: u64| ExecuteMsg::Bid {
+            id: "test-bid".into(),
+            price: Decimal::from_ratio(1u128, 1u128),
+            expires_at: Some(expires_at),
+        };
         let err = execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("admin", &[funds]), // Admin cannot place ask orders
-            ExecuteMsg::Ask {
-                id: "test-ask".into(),
-                price: Uint128(1),
-            },
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            bid(now),
         )
         .unwrap_err();
-
-        // Ensure we go the expected error
         match err {
-            ContractError::Unauthorized {} => {}
+            ContractError::InvalidExpiration { message } => {
+                assert_eq!(message, "expiration must be after the current block time")
+            }
             _ => panic!("unexpected error type"),
         }
-    }
-
-    #[test]
-    fn unauthorized_match() {
-        // Create mock deps.
-        let mut deps = mock_dependencies(&[]);
 
-        // Init
-        instantiate(
+        // Place a bid that expires in 10 seconds, and a crossing ask that doesn't expire
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("admin", &[]),
-            InitMsg {
-                bid_denom: "stablecoin".into(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            bid(now + 10),
+        )
+        .unwrap();
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("asker", &[coin(10_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
 
-        // Execute a match
-        let err = execute(
+        // Match after the bid has expired
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(20);
+        let res = execute(
             deps.as_mut(),
-            mock_env(),
-            mock_info("asker", &[]), // Admin must execute match
-            ExecuteMsg::Match {},
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
         )
-        .unwrap_err();
+        .unwrap();
 
-        // Ensure we go the expected error
-        match err {
-            ContractError::Unauthorized {} => {}
-            _ => panic!("unexpected error type"),
-        }
+        // Ensure the bid was refunded instead of matched
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
+                amount: vec![coin(10, "stablecoin")],
+                to_address: "bidder".into(),
+            })]
+        );
+        assert!(!res.attributes.iter().any(|a| a.key == "orderbook.match"));
+        assert!(res
+            .attributes
+            .iter()
+            .any(|a| a.key == "orderbook.expired" && a.value == "test-bid"));
+
+        // Ensure only the ask is left resting
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert_eq!(rep.ask_orders.len(), 1);
     }
 
     #[test]
-    fn invalid_bid_amount() {
+    fn match_reaps_expired_orders() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -1050,75 +6111,81 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Attempt to buy 1 hash at 15 stablecoin/hash price yielding fractional nhash proceeds
-        let funds = coin(1, "stablecoin");
-        let err = execute(
+        // Place a bid that expires in 10 seconds, and a crossing bid and ask that don't expire
+        let now = mock_env().block.time.nanos() / 1_000_000_000;
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("bidder", &[funds]),
+            mock_info("expiring-bidder", &[coin(10, "stablecoin")]),
             ExecuteMsg::Bid {
-                id: "test-bid".into(),
-                price: Uint128(15),
+                id: "expiring-bid".into(),
+                price: Decimal::from_ratio(2u128, 1u128),
+                expires_at: Some(now + 10),
             },
         )
-        .unwrap_err();
-
-        // Ensure we go the expected error
-        match err {
-            ContractError::InvalidFunds { message } => {
-                assert_eq!(message, "bid price must yield an integral for proceeds")
-            }
-            _ => panic!("unexpected error type"),
-        }
-    }
-
-    #[test]
-    fn invalid_bid_amount_increment() {
-        // Create mock deps.
-        let mut deps = mock_dependencies(&[]);
-
-        // Init
-        instantiate(
+        .unwrap();
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("admin", &[]),
-            InitMsg {
-                bid_denom: "stablecoin".into(),
+            mock_info("bidder", &[coin(10, "stablecoin")]),
+            ExecuteMsg::Bid {
+                id: "test-bid".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
         .unwrap();
-
-        // Attempt to buy < 1hash at 15 stablecoin/hash price
-        let funds = coin(3, "stablecoin");
-        let err = execute(
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("bidder", &[funds]),
-            ExecuteMsg::Bid {
-                id: "test-bid".into(),
-                price: Uint128(15),
+            mock_info("asker", &[coin(10_000_000_000, "nhash")]),
+            ExecuteMsg::Ask {
+                id: "test-ask".into(),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: None,
             },
         )
-        .unwrap_err();
+        .unwrap();
 
-        // Ensure we go the expected error
-        match err {
-            ContractError::InvalidFunds { message } => {
-                assert_eq!(
-                    message,
-                    "funds must yield a bid amount in the required increments"
-                )
-            }
-            _ => panic!("unexpected error type"),
-        }
+        // Match once after the first bid has expired
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(20);
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("admin", &[]),
+            ExecuteMsg::Match { max_matches: None },
+        )
+        .unwrap();
+
+        // Ensure the expired bid was refunded and the remaining pair was matched
+        assert_eq!(
+            res.messages[0],
+            CosmosMsg::Bank(BankMsg::Send {
+                amount: vec![coin(10, "stablecoin")],
+                to_address: "expiring-bidder".into(),
+            })
+        );
+        assert!(res
+            .attributes
+            .iter()
+            .any(|a| a.key == "orderbook.expired" && a.value == "expiring-bid"));
+        assert!(res.attributes.iter().any(|a| a.key == "orderbook.match"));
+
+        // Ensure the book is empty
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.bid_orders.is_empty());
+        assert!(rep.ask_orders.is_empty());
     }
 
     #[test]
-    fn invalid_ask_amount() {
+    fn reap_expired() {
         // Create mock deps.
         let mut deps = mock_dependencies(&[]);
 
@@ -1129,30 +6196,60 @@ mod tests {
             mock_info("admin", &[]),
             InitMsg {
                 bid_denom: "stablecoin".into(),
+                ..Default::default()
             },
         )
         .unwrap();
 
-        // Attempt to sell < 1 hash at 1 stablecoin/hash price
-        let funds = coin(123456789, "nhash");
-        let err = execute(
+        // Place an ask that expires in 5 seconds
+        let now = mock_env().block.time.nanos() / 1_000_000_000;
+        execute(
             deps.as_mut(),
             mock_env(),
-            mock_info("asker", &[funds]),
+            mock_info("asker", &[coin(2_000_000_000, "nhash")]),
             ExecuteMsg::Ask {
                 id: "test-ask".into(),
-                price: Uint128(1),
+                price: Decimal::from_ratio(1u128, 1u128),
+                expires_at: Some(now + 5),
             },
         )
-        .unwrap_err();
+        .unwrap();
 
-        // Ensure we go the expected error
-        match err {
-            ContractError::InvalidFunds { message } => assert_eq!(
-                message,
-                "ask amount must be > 0 in the required increments: got 123456789"
-            ),
-            _ => panic!("unexpected error type"),
+        // Reap at the expiration time and after it, even while paused
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("admin", &[]),
+            ExecuteMsg::SetPaused { paused: true },
+        )
+        .unwrap();
+        for (secs, expected_reaped) in [(5, "0"), (6, "1")] {
+            let mut env = mock_env();
+            env.block.time = env.block.time.plus_seconds(secs);
+            let res = execute(
+                deps.as_mut(),
+                env,
+                mock_info("anyone", &[]),
+                ExecuteMsg::ReapExpired {},
+            )
+            .unwrap();
+            let reaped = res.attributes.iter().find(|a| a.key == "reaped").unwrap();
+            assert_eq!(reaped.value, expected_reaped);
+            if expected_reaped == "1" {
+                // Ensure the asker got their hash back
+                assert_eq!(
+                    res.messages,
+                    vec![CosmosMsg::Bank(BankMsg::Send {
+                        amount: vec![coin(2_000_000_000, "nhash")],
+                        to_address: "asker".into(),
+                    })]
+                );
+            }
         }
+
+        // Ensure the ask was removed
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetOrderbook {}).unwrap();
+        let rep: Orderbook = from_binary(&bin).unwrap();
+        assert!(rep.ask_orders.is_empty());
     }
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -26,4 +26,25 @@ pub enum ContractError {
 
     #[error("BidClosed")]
     BidClosed {},
+
+    #[error("OrderNotFound: {id:?}")]
+    OrderNotFound { id: String },
+
+    #[error("InvalidOrderId: {message:?}")]
+    InvalidOrderId { message: String },
+
+    #[error("BookFull")]
+    BookFull {},
+
+    #[error("NoCounterOrders")]
+    NoCounterOrders {},
+
+    #[error("OrderTooSmall: {min:?}")]
+    OrderTooSmall { min: Uint128 },
+
+    #[error("Paused")]
+    Paused {},
+
+    #[error("InvalidExpiration: {message:?}")]
+    InvalidExpiration { message: String },
 }
@@ -1,39 +1,126 @@
-use crate::state::{AskOrder, BidOrder};
-use cosmwasm_std::Uint128;
+use crate::state::{AskOrder, BidOrder, DenomAlias};
+use cosmwasm_std::{Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub bid_denom: String,
+    pub ask_denom: Option<String>, // The denom sold by asks (default nhash)
+    pub ask_increment: Option<Uint128>, // The ask funds increment (default 1 hash)
+    pub min_fill: Option<Uint128>, // Min ask denom a match must fill (default 0)
+    pub max_open_orders: Option<u64>, // Max bids plus asks open at once (default unlimited)
+    pub max_iterations: Option<u64>, // Max pairs considered per match (default unlimited)
+    pub fee_bps: Option<u16>,      // Fee taken from each side of a match (default 0)
+    pub fee_collector: Option<String>, // Receives trading fees (default admin)
+    pub auto_match: Option<bool>, // Match new orders against earlier blocks' orders (default false)
+    pub min_bid_funds: Option<Uint128>, // Min bid denom an order must hold (default 0)
+    pub min_ask_funds: Option<Uint128>, // Min ask denom an order must hold (default 0)
+    pub denom_aliases: Option<Vec<DenomAlias>>, // Display names for denoms (default none)
+    pub entry_fee: Option<Uint128>, // Bid denom charged to place each bid or ask (default 0)
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
-    Bid { id: String, price: Uint128 }, // Number of stablecoins offered for 1 hash
-    Ask { id: String, price: Uint128 }, // Number of stablecoins requested for 1 hash
-    Match {},                           // Match each ask to >= 1 bids
+    // Number of stablecoins offered for 1 hash; may be fractional
+    Bid {
+        id: String,
+        price: Decimal,
+        expires_at: Option<u64>, // Remove and refund the order after this block time (seconds)
+    },
+    // Number of stablecoins requested for 1 hash; may be fractional
+    Ask {
+        id: String,
+        price: Decimal,
+        expires_at: Option<u64>, // Remove and refund the order after this block time (seconds)
+    },
+    // Match each ask to >= 1 bids
+    Match {
+        max_matches: Option<u32>, // Stop after this many matches; match again to continue
+    },
+    // Cancel a bid, refunding any unmatched funds
+    CancelBid {
+        id: String,
+    },
+    // Cancel an ask, returning any unmatched funds
+    CancelAsk {
+        id: String,
+    },
+    // Buy at the best ask prices when matched
+    MarketBid {
+        id: String,
+    },
+    // Sell at the best bid prices when matched
+    MarketAsk {
+        id: String,
+    },
+    // Halt or resume new orders and matching (admin only)
+    SetPaused {
+        paused: bool,
+    },
+    // Re-price a resting bid; it loses its time priority
+    AmendBid {
+        id: String,
+        new_price: Decimal,
+    },
+    // Re-price a resting ask; it loses its time priority
+    AmendAsk {
+        id: String,
+        new_price: Decimal,
+    },
+    // Remove expired orders, refunding their funds (anyone can reap)
+    ReapExpired {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetBidOrders {},
-    GetAskOrders {},
+    GetBidOrders {
+        start_after: Option<String>, // Page by order id (storage key order)
+        limit: Option<u32>,          // Max orders to return (default 30, max 100)
+    },
+    GetAskOrders {
+        start_after: Option<String>, // Page by order id (storage key order)
+        limit: Option<u32>,          // Max orders to return (default 30, max 100)
+    },
     GetOrderbook {},
+    GetOrderEscrow {
+        id: String,
+    },
+    GetMatchablePairsCount {},
+    FindOrder {
+        id: String,
+    },
+    GetSpreadBps {},
+    GetOrderAge {
+        id: String,
+    },
+    GetUnmatchableOrders {},
+    GetDepth {
+        limit: Option<u32>, // Max price levels per side (default 30, max 100)
+    },
+    GetOrdersByAddress {
+        address: String,
+    },
+    SimulateMatch {},
+    GetImbalance {},
+    GetMarketStats {},
+    GetConfig {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct BidOrders {
     pub bid_orders: Vec<BidOrder>,
+    pub denom_aliases: Vec<DenomAlias>, // Display names for the order denoms
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct AskOrders {
     pub ask_orders: Vec<AskOrder>,
+    pub denom_aliases: Vec<DenomAlias>, // Display names for the order denoms
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -41,4 +128,118 @@ pub struct AskOrders {
 pub struct Orderbook {
     pub bid_orders: Vec<BidOrder>,
     pub ask_orders: Vec<AskOrder>,
+    pub denom_aliases: Vec<DenomAlias>, // Display names for the order denoms
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderSide {
+    Bid,
+    Ask,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderEscrow {
+    pub id: String,
+    pub side: OrderSide,
+    pub funds: Uint128, // The funds still held by the contract for the order
+    pub funds_denom: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MatchablePairsCount {
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FoundOrder {
+    Bid(BidOrder),
+    Ask(AskOrder),
+    NotFound,
+}
+
+/// Migrate the contract, upgrading any orders persisted under an older schema.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {
+    pub ask_increment: Option<Uint128>, // Rescale resting orders to a new ask increment
+    pub reconcile: Option<bool>,        // Report expected vs actual escrow balances per denom
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SpreadBps {
+    pub spread_bps: Option<i64>, // Negative when the book is crossed, None if a side is empty
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct OrderAge {
+    pub id: String,
+    pub age: u64, // Seconds since the order was placed
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UnmatchableReason {
+    NoCounterOrders,   // The other side of the book is empty
+    PriceDoesNotCross, // No order on the other side crosses the price
+    SelfTradeOnly,     // Only orders from the same account cross the price
+    BelowMinFill,      // Crossing orders would fill less than the min fill or a single increment
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UnmatchableOrder {
+    pub id: String,
+    pub side: OrderSide,
+    pub reason: UnmatchableReason,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UnmatchableOrders {
+    pub orders: Vec<UnmatchableOrder>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PriceLevel {
+    pub price: Decimal,
+    pub total_funds: Uint128, // The funds held by all orders at this price
+    pub order_count: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Depth {
+    pub bids: Vec<PriceLevel>, // Highest price first
+    pub asks: Vec<PriceLevel>, // Lowest price first
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct Imbalance {
+    pub bid_volume: Uint128,    // The ask denom wanted by resting limit bids
+    pub ask_volume: Uint128,    // The ask denom offered by resting limit asks
+    pub ratio: Option<Decimal>, // Bid volume divided by ask volume, None if there are no asks
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SimulatedFill {
+    pub bid_id: String,
+    pub ask_id: String,
+    pub fill_price: Option<Decimal>, // The price per ask increment, None if no ask denom moved
+    pub nhash_moved: Uint128,        // The ask denom sent to the bidder
+    pub stablecoin_moved: Uint128,   // The bid denom taken from the bid, including any fee
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SimulatedMatch {
+    pub fills: Vec<SimulatedFill>, // In the order a match would make them
 }
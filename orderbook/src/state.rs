@@ -1,4 +1,4 @@
-use cosmwasm_std::{Addr, Uint128};
+use cosmwasm_std::{Addr, Decimal, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -10,6 +10,10 @@ use cosmwasm_storage::{
 pub static CONFIG_KEY: &[u8] = b"config";
 pub static BID_KEY: &[u8] = b"bid";
 pub static ASK_KEY: &[u8] = b"ask";
+pub static STATS_KEY: &[u8] = b"stats";
+
+/// The height recorded for orders placed before block heights were tracked.
+pub const UNKNOWN_HEIGHT: u64 = 0;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
@@ -17,64 +21,243 @@ pub struct State {
     pub ask_increment: Uint128,
     pub bid_denom: String,
     pub contract_admin: Addr,
+    #[serde(default)]
+    pub min_fill: Uint128, // Matches filling less nhash than this are skipped
+    pub max_open_orders: Option<u64>, // The max bids plus asks that can be open at once
+    pub max_iterations: Option<u64>,  // The max bid/ask pairs a single match will consider
+    #[serde(default)]
+    pub fee_bps: u16, // The fee taken from each side of a match, in basis points
+    pub fee_collector: Addr,          // Receives trading fees
+    #[serde(default)]
+    pub auto_match: bool, // Match new orders against orders from earlier blocks
+    #[serde(default)]
+    pub min_bid_funds: Uint128, // Bids with less funds than this are rejected
+    #[serde(default)]
+    pub min_ask_funds: Uint128, // Asks with less funds than this are rejected
+    #[serde(default)]
+    pub paused: bool, // Halts new orders and matching; cancels are still allowed
+    #[serde(default)]
+    pub denom_aliases: Vec<DenomAlias>, // Display names for denoms
+    #[serde(default)]
+    pub entry_fee: Uint128, // Bid denom charged to place an order, sent to the fee collector
+}
+
+/// Config schema persisted before `fee_collector` was added; used by migrations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
+    pub ask_denom: String,
+    pub ask_increment: Uint128,
+    pub bid_denom: String,
+    pub contract_admin: Addr,
+    #[serde(default)]
+    pub min_fill: Uint128,
+    pub max_open_orders: Option<u64>,
+    pub max_iterations: Option<u64>,
+}
+
+impl LegacyState {
+    pub fn upgrade(self) -> State {
+        State {
+            ask_denom: self.ask_denom,
+            ask_increment: self.ask_increment,
+            bid_denom: self.bid_denom,
+            fee_collector: self.contract_admin.clone(),
+            contract_admin: self.contract_admin,
+            min_fill: self.min_fill,
+            max_open_orders: self.max_open_orders,
+            max_iterations: self.max_iterations,
+            fee_bps: 0,
+            auto_match: false,
+            min_bid_funds: Uint128::zero(),
+            min_ask_funds: Uint128::zero(),
+            paused: false,
+            denom_aliases: vec![],
+            entry_fee: Uint128::zero(),
+        }
+    }
+}
+
+/// A display name for a denom. This is metadata only; orders always use the raw denom.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DenomAlias {
+    pub denom: String,
+    pub alias: String,
+}
+
+/// Cumulative totals across every match, for analytics. Missing until the first match.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct MarketStats {
+    pub total_nhash_matched: Uint128, // The ask denom sent to bidders
+    pub total_stablecoin_matched: Uint128, // The bid denom taken from bids, including fees
+    pub match_count: u64,
 }
 
 /// Persisted bid order.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BidOrder {
     pub id: String,
-    pub price: Uint128,
+    pub price: Decimal, // The price per ask increment
     pub ts: u64,
     pub bidder: Addr,
     pub funds: Uint128, // The stablecoin available for transfer
     pub funds_denom: String,
     pub proceeds: Uint128, // The proceeds for the bid
+    pub proceeds_denom: String,
+    #[serde(default)]
+    pub height: u64, // The block height the order was placed at, or UNKNOWN_HEIGHT
+    #[serde(default)]
+    pub market: bool, // Market bids are priced by the ask they match
+    #[serde(default)]
+    pub expires_at: Option<u64>, // The block time (seconds) after which the order is removed
 }
 
 impl BidOrder {
     pub fn is_closed(&self) -> bool {
         self.proceeds.is_zero() && self.funds.is_zero()
     }
+
+    pub fn is_expired(&self, ts: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < ts)
+    }
 }
 
 /// Persisted ask order.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AskOrder {
     pub id: String,
-    pub price: Uint128,
+    pub price: Decimal, // The price per ask increment
     pub ts: u64,
     pub asker: Addr,
     pub funds: Uint128, // The nhash available for transfer
     pub funds_denom: String,
     pub proceeds: Uint128, // The proceeds for the ask
+    pub proceeds_denom: String,
+    #[serde(default)]
+    pub height: u64, // The block height the order was placed at, or UNKNOWN_HEIGHT
+    #[serde(default)]
+    pub market: bool, // Market asks are priced by the bid they match
+    #[serde(default)]
+    pub expires_at: Option<u64>, // The block time (seconds) after which the order is removed
 }
 
 impl AskOrder {
     pub fn is_closed(&self) -> bool {
         self.proceeds.is_zero() && self.funds.is_zero()
     }
+
+    pub fn is_expired(&self, ts: u64) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at < ts)
+    }
+}
+
+/// Bid order schema persisted before `proceeds_denom` was added; used by migrations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyBidOrder {
+    pub id: String,
+    pub price: Decimal, // Integer prices stored by older releases also parse as decimals
+    pub ts: u64,
+    pub bidder: Addr,
+    pub funds: Uint128,
+    pub funds_denom: String,
+    pub proceeds: Uint128,
+}
+
+impl LegacyBidOrder {
+    pub fn upgrade(self, proceeds_denom: &str) -> BidOrder {
+        BidOrder {
+            id: self.id,
+            price: self.price,
+            ts: self.ts,
+            bidder: self.bidder,
+            funds: self.funds,
+            funds_denom: self.funds_denom,
+            proceeds: self.proceeds,
+            proceeds_denom: proceeds_denom.into(),
+            height: UNKNOWN_HEIGHT,
+            market: false,
+            expires_at: None,
+        }
+    }
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+/// Ask order schema persisted before `proceeds_denom` was added; used by migrations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyAskOrder {
+    pub id: String,
+    pub price: Decimal, // Integer prices stored by older releases also parse as decimals
+    pub ts: u64,
+    pub asker: Addr,
+    pub funds: Uint128,
+    pub funds_denom: String,
+    pub proceeds: Uint128,
+}
+
+impl LegacyAskOrder {
+    pub fn upgrade(self, proceeds_denom: &str) -> AskOrder {
+        AskOrder {
+            id: self.id,
+            price: self.price,
+            ts: self.ts,
+            asker: self.asker,
+            funds: self.funds,
+            funds_denom: self.funds_denom,
+            proceeds: self.proceeds,
+            proceeds_denom: proceeds_denom.into(),
+            height: UNKNOWN_HEIGHT,
+            market: false,
+            expires_at: None,
+        }
+    }
+}
+
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
     singleton_read(storage, CONFIG_KEY)
 }
 
-pub fn bid_orders(storage: &mut dyn Storage) -> Bucket<BidOrder> {
+pub fn legacy_config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, LegacyState> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+pub fn market_stats(storage: &mut dyn Storage) -> Singleton<'_, MarketStats> {
+    singleton(storage, STATS_KEY)
+}
+
+pub fn market_stats_read(storage: &dyn Storage) -> ReadonlySingleton<'_, MarketStats> {
+    singleton_read(storage, STATS_KEY)
+}
+
+pub fn bid_orders(storage: &mut dyn Storage) -> Bucket<'_, BidOrder> {
+    bucket(storage, BID_KEY)
+}
+
+pub fn bid_orders_read(storage: &dyn Storage) -> ReadonlyBucket<'_, BidOrder> {
+    bucket_read(storage, BID_KEY)
+}
+
+pub fn ask_orders(storage: &mut dyn Storage) -> Bucket<'_, AskOrder> {
+    bucket(storage, ASK_KEY)
+}
+
+pub fn ask_orders_read(storage: &dyn Storage) -> ReadonlyBucket<'_, AskOrder> {
+    bucket_read(storage, ASK_KEY)
+}
+
+pub fn legacy_bid_orders(storage: &mut dyn Storage) -> Bucket<'_, LegacyBidOrder> {
     bucket(storage, BID_KEY)
 }
 
-pub fn bid_orders_read(storage: &dyn Storage) -> ReadonlyBucket<BidOrder> {
+pub fn legacy_bid_orders_read(storage: &dyn Storage) -> ReadonlyBucket<'_, LegacyBidOrder> {
     bucket_read(storage, BID_KEY)
 }
 
-pub fn ask_orders(storage: &mut dyn Storage) -> Bucket<AskOrder> {
+pub fn legacy_ask_orders(storage: &mut dyn Storage) -> Bucket<'_, LegacyAskOrder> {
     bucket(storage, ASK_KEY)
 }
 
-pub fn ask_orders_read(storage: &dyn Storage) -> ReadonlyBucket<AskOrder> {
+pub fn legacy_ask_orders_read(storage: &dyn Storage) -> ReadonlyBucket<'_, LegacyAskOrder> {
     bucket_read(storage, ASK_KEY)
 }
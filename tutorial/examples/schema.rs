@@ -3,7 +3,9 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use tutorial::msg::{ExecuteMsg, InitMsg, QueryMsg, QueryResponse};
+use tutorial::msg::{
+    ExecuteMsg, FeesCollectedResponse, InitMsg, MigrateMsg, QueryMsg, QueryResponse,
+};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,4 +17,6 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(QueryResponse), &out_dir);
+    export_schema(&schema_for!(FeesCollectedResponse), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
 }
@@ -1,13 +1,13 @@
 use cosmwasm_std::{
-    attr, coin, to_binary, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo,
-    Response, StdError, StdResult,
+    attr, coin, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut, Env,
+    MessageInfo, Response, StdError, StdResult, Uint128,
 };
 use provwasm_std::{bind_name, NameBinding, ProvenanceMsg};
 use std::ops::Mul;
 
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InitMsg, QueryMsg};
-use crate::state::{config, config_read, State};
+use crate::msg::{ExecuteMsg, FeesCollectedResponse, InitMsg, MigrateMsg, QueryMsg};
+use crate::state::{config, config_read, legacy_config_read, FeeSplit, RoundingMode, State};
 
 /// Initialize the contract
 pub fn instantiate(
@@ -22,13 +22,15 @@ pub fn instantiate(
         return Err(StdError::generic_err(errm));
     }
 
-    // Ensure there are limits on fees.
-    if msg.fee_percent.is_zero() || msg.fee_percent > Decimal::percent(25) {
-        return Err(StdError::generic_err(
-            "fee percent must be > 0.0 and <= 0.25",
-        ));
+    // Ensure at least one purchase denom is accepted
+    if msg.purchase_denoms.is_empty() {
+        let errm = "at least one purchase denom is required";
+        return Err(StdError::generic_err(errm));
     }
 
+    // Ensure there are limits on fees.
+    validate_fee_percent(msg.fee_percent)?;
+
     // Ensure the merchant address is not also the fee collection address
     if msg.merchant_address == info.sender {
         return Err(StdError::generic_err(
@@ -39,11 +41,46 @@ pub fn instantiate(
     // Create and save contract config state. The fee collection address represents the network
     // (ie they get paid fees), thus they must be the message sender.
     let merchant_address = deps.api.addr_validate(&msg.merchant_address)?;
+
+    // Ensure each fee split is for a purchase denom, and that each denom has a single fee split
+    // that pays out the whole fee.
+    let mut fee_splits: Vec<FeeSplit> = vec![];
+    for split in msg.fee_splits.unwrap_or_default() {
+        if !msg.purchase_denoms.contains(&split.denom) {
+            let errm = format!("fee split denom is not a purchase denom: {}", split.denom);
+            return Err(StdError::generic_err(errm));
+        }
+        if fee_splits.iter().any(|s| s.denom == split.denom) {
+            let errm = format!("duplicate fee split denom: {}", split.denom);
+            return Err(StdError::generic_err(errm));
+        }
+        let total = split
+            .collectors
+            .iter()
+            .fold(Decimal::zero(), |total, (_, weight)| total + *weight);
+        if total != Decimal::one() {
+            let errm = format!("fee split weights for {} must sum to 1", split.denom);
+            return Err(StdError::generic_err(errm));
+        }
+        let collectors = split
+            .collectors
+            .iter()
+            .map(|(address, weight)| Ok((deps.api.addr_validate(address)?, *weight)))
+            .collect::<StdResult<Vec<_>>>()?;
+        fee_splits.push(FeeSplit {
+            denom: split.denom,
+            collectors,
+        });
+    }
+
     config(deps.storage).save(&State {
-        purchase_denom: msg.purchase_denom,
+        purchase_denoms: msg.purchase_denoms,
         merchant_address,
         fee_collection_address: info.sender,
         fee_percent: msg.fee_percent,
+        fees_collected: vec![],
+        fee_rounding: msg.fee_rounding.unwrap_or_default(),
+        fee_splits,
     })?;
 
     // Create a message that will bind a restricted name to the contract address.
@@ -62,6 +99,16 @@ pub fn instantiate(
     })
 }
 
+// Ensure a fee percentage is within the allowed range.
+fn validate_fee_percent(fee_percent: Decimal) -> StdResult<()> {
+    if fee_percent.is_zero() || fee_percent > Decimal::percent(25) {
+        return Err(StdError::generic_err(
+            "fee percent must be > 0.0 and <= 0.25",
+        ));
+    }
+    Ok(())
+}
+
 /// Handle purchase messages.
 pub fn execute(
     deps: DepsMut,
@@ -71,6 +118,9 @@ pub fn execute(
 ) -> Result<Response<BankMsg>, ContractError> {
     match msg {
         ExecuteMsg::Purchase { id } => try_purchase(deps, env, info, id),
+        ExecuteMsg::UpdateMerchant { merchant_address } => {
+            try_update_merchant(deps, info, merchant_address)
+        }
     }
 }
 
@@ -93,39 +143,47 @@ fn try_purchase(
 
     // Ensure the funds have the required amount and denomination
     for funds in info.funds.iter() {
-        if funds.amount.is_zero() || funds.denom != state.purchase_denom {
+        if funds.amount.is_zero() || !state.purchase_denoms.contains(&funds.denom) {
             let errm = format!("invalid purchase funds: {}{}", funds.amount, funds.denom);
             return Err(ContractError::Std(StdError::generic_err(errm)));
         }
     }
 
-    // Calculate amounts and create bank transfers to the merchant account
-    let transfers = CosmosMsg::Bank(BankMsg::Send {
-        to_address: state.merchant_address.to_string(),
-        amount: info
-            .funds
-            .iter()
-            .map(|sent| {
-                let fees = sent.amount.mul(fee_pct).u128();
-                coin(sent.amount.u128() - fees, sent.denom.clone())
+    // Split each coin sent, transferring the fee to the collectors for its denom and the rest of
+    // the purchase amount to the merchant.
+    let mut messages = vec![];
+    let mut fee_amounts: Vec<Coin> = vec![];
+    for sent in info.funds.iter() {
+        let fee = fee_amount(sent.amount, fee_pct, &state.fee_rounding);
+        let fee = coin(fee.u128(), sent.denom.clone());
+        messages.push(CosmosMsg::Bank(BankMsg::Send {
+            to_address: state.merchant_address.to_string(),
+            amount: vec![coin(
+                sent.amount.u128() - fee.amount.u128(),
+                sent.denom.clone(),
+            )],
+        }));
+        messages.extend(split_fee(&fee, &state).into_iter().map(|(address, share)| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: address.to_string(),
+                amount: vec![share],
             })
-            .collect(),
-    });
-
-    // Calculate fees and create bank transfers to the fee collection account
-    let fees = CosmosMsg::Bank(BankMsg::Send {
-        to_address: state.fee_collection_address.to_string(),
-        amount: info
-            .funds
-            .iter()
-            .map(|sent| coin(sent.amount.mul(fee_pct).u128(), sent.denom.clone()))
-            .collect(),
-    });
+        }));
+        fee_amounts.push(fee);
+    }
+
+    // Add the fees to the cumulative totals.
+    config(deps.storage).update(|mut state| -> StdResult<_> {
+        for fee in fee_amounts {
+            add_fee(&mut state.fees_collected, fee);
+        }
+        Ok(state)
+    })?;
 
     // Return a response that will dispatch the transfers to the bank module and emit events.
     Ok(Response {
         submessages: vec![],
-        messages: vec![transfers, fees],
+        messages,
         attributes: vec![
             attr("tutorial-v2", ""),
             attr("action", "purchase"),
@@ -136,6 +194,89 @@ fn try_purchase(
     })
 }
 
+// Repoint purchases to a new merchant account. Only the fee collection address may do this.
+fn try_update_merchant(
+    deps: DepsMut,
+    info: MessageInfo,
+    merchant_address: String,
+) -> Result<Response<BankMsg>, ContractError> {
+    let merchant_address = deps.api.addr_validate(&merchant_address)?;
+    config(deps.storage).update(|mut state| -> Result<_, ContractError> {
+        if info.sender != state.fee_collection_address {
+            return Err(ContractError::Unauthorized {});
+        }
+        // Ensure the merchant address is not also the fee collection address
+        if merchant_address == state.fee_collection_address {
+            return Err(ContractError::Std(StdError::generic_err(
+                "merchant address can't be the fee collection address",
+            )));
+        }
+        state.merchant_address = merchant_address.clone();
+        Ok(state)
+    })?;
+
+    Ok(Response {
+        submessages: vec![],
+        messages: vec![],
+        attributes: vec![
+            attr("tutorial-v2", ""),
+            attr("action", "update_merchant"),
+            attr("merchant_address", merchant_address),
+        ],
+        data: None,
+    })
+}
+
+// Calculate the fee for an amount, rounding in the configured direction. Rounding up is done by
+// rounding the merchant's share down, so the fee and merchant amounts always add up to the total.
+fn fee_amount(amount: Uint128, fee_pct: Decimal, rounding: &RoundingMode) -> Uint128 {
+    match rounding {
+        RoundingMode::Down => amount.mul(fee_pct),
+        RoundingMode::Up => Uint128(amount.u128() - amount.mul(Decimal::one() - fee_pct).u128()),
+    }
+}
+
+// Divide a fee among the collectors for its denom, or send it all to the fee collection account
+// if the denom has no split. Shares are rounded down and the last collector gets the remainder,
+// so the shares always add up to the fee.
+fn split_fee(fee: &Coin, state: &State) -> Vec<(Addr, Coin)> {
+    let split = match state
+        .fee_splits
+        .iter()
+        .find(|split| split.denom == fee.denom)
+    {
+        Some(split) => split,
+        None => return vec![(state.fee_collection_address.clone(), fee.clone())],
+    };
+    let mut remaining = fee.amount.u128();
+    let last = split.collectors.len() - 1;
+    split
+        .collectors
+        .iter()
+        .enumerate()
+        .map(|(i, (address, weight))| {
+            let share = if i == last {
+                remaining
+            } else {
+                fee.amount.mul(*weight).u128()
+            };
+            remaining -= share;
+            (address.clone(), coin(share, fee.denom.clone()))
+        })
+        .collect()
+}
+
+// Merge a fee into the collected totals, adding to any existing entry for the same denom.
+fn add_fee(fees: &mut Vec<Coin>, fee: Coin) {
+    if fee.amount.is_zero() {
+        return;
+    }
+    match fees.iter_mut().find(|c| c.denom == fee.denom) {
+        Some(total) => total.amount += fee.amount,
+        None => fees.push(fee),
+    }
+}
+
 /// Query for contract state.
 pub fn query(
     deps: Deps,
@@ -148,15 +289,41 @@ pub fn query(
             let json = to_binary(&state)?;
             Ok(json)
         }
+        QueryMsg::GetFeesCollected {} => {
+            let state = config_read(deps.storage).load()?;
+            let json = to_binary(&FeesCollectedResponse {
+                fees: state.fees_collected,
+            })?;
+            Ok(json)
+        }
     }
 }
 
+/// Called when migrating a contract instance to a new code ID.
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // Ensure the updated fee percentage is within the same range as init.
+    validate_fee_percent(msg.new_fee_percent)?;
+
+    // Load state, upgrading state persisted before multiple purchase denoms were accepted.
+    let mut state = match config_read(deps.storage).load() {
+        Ok(state) => state,
+        Err(_) => legacy_config_read(deps.storage).load()?.upgrade(),
+    };
+
+    // Update fees and save.
+    state.fee_percent = msg.new_fee_percent;
+    config(deps.storage).save(&state)?;
+
+    // Return the default success response
+    Ok(Response::default())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::msg::QueryResponse;
+    use crate::msg::{FeeSplitInit, QueryResponse};
     use cosmwasm_std::testing::{mock_env, mock_info};
-    use cosmwasm_std::{from_binary, Addr};
+    use cosmwasm_std::{from_binary, Addr, Storage};
     use provwasm_mocks::mock_dependencies;
     use provwasm_std::{NameMsgParams, ProvenanceMsgParams};
 
@@ -172,9 +339,11 @@ mod tests {
             mock_info("feebucket", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap();
@@ -205,9 +374,11 @@ mod tests {
             mock_info("merchant", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap_err();
@@ -233,9 +404,11 @@ mod tests {
             mock_info("feebucket", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(37), // error: > 25%
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap_err();
@@ -261,9 +434,11 @@ mod tests {
             mock_info("feebucket", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap(); // Panics on error
@@ -274,7 +449,7 @@ mod tests {
 
         // Ensure the expected init fields were properly stored.
         assert_eq!(resp.merchant_address, Addr::unchecked("merchant"));
-        assert_eq!(resp.purchase_denom, "pcoin");
+        assert_eq!(resp.purchase_denoms, vec!["pcoin"]);
         assert_eq!(resp.fee_collection_address, Addr::unchecked("feebucket"));
         assert_eq!(resp.fee_percent, Decimal::percent(10));
     }
@@ -291,9 +466,11 @@ mod tests {
             mock_info("feebucket", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap();
@@ -341,6 +518,74 @@ mod tests {
         })
     }
 
+    #[test]
+    fn handle_multiple_purchase_denoms() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create config state, accepting two purchase denoms
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into(), "qcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
+            },
+        )
+        .unwrap();
+
+        // Make a purchase with both denoms
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("consumer", &[coin(100, "pcoin"), coin(50, "qcoin")]),
+            ExecuteMsg::Purchase {
+                id: "a7918172-ac09-43f6-bc4b-7ac2fbad17e9".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure each coin was split between the merchant and the fee bucket.
+        // 10% fees => 90pcoin and 45qcoin for the merchant, 10pcoin and 5qcoin for the fee bucket.
+        let send = |to_address: &str, amount: Coin| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: to_address.into(),
+                amount: vec![amount],
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                send("merchant", coin(90, "pcoin")),
+                send("feebucket", coin(10, "pcoin")),
+                send("merchant", coin(45, "qcoin")),
+                send("feebucket", coin(5, "qcoin")),
+            ]
+        );
+
+        // Ensure a purchase with an unsupported denom is rejected
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("consumer", &[coin(100, "pcoin"), coin(100, "fakecoin")]),
+            ExecuteMsg::Purchase {
+                id: "a7918172-ac09-43f6-bc4b-7ac2fbad17e9".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "invalid purchase funds: 100fakecoin")
+            }
+            _ => panic!("unexpected handle error"),
+        }
+    }
+
     #[test]
     fn handle_invalid_funds() {
         // Create mocks
@@ -353,9 +598,11 @@ mod tests {
             mock_info("feebucket", &[]),
             InitMsg {
                 contract_name: "tutorial.sc.pb".into(),
-                purchase_denom: "pcoin".into(),
+                purchase_denoms: vec!["pcoin".into()],
                 merchant_address: "merchant".into(),
                 fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
             },
         )
         .unwrap();
@@ -417,4 +664,388 @@ mod tests {
             _ => panic!("unexpected handle error"),
         }
     }
+
+    #[test]
+    fn query_fees_collected() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create config state
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
+            },
+        )
+        .unwrap();
+
+        // Make two purchases of 100pcoin and 50pcoin
+        for amount in [100, 50] {
+            execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("consumer", &[coin(amount, "pcoin")]),
+                ExecuteMsg::Purchase {
+                    id: "a7918172-ac09-43f6-bc4b-7ac2fbad17e9".into(),
+                },
+            )
+            .unwrap();
+        }
+
+        // Ensure the fees from both purchases were accumulated (10pcoin + 5pcoin).
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetFeesCollected {}).unwrap();
+        let resp: FeesCollectedResponse = from_binary(&bin).unwrap();
+        assert_eq!(resp.fees, vec![coin(15, "pcoin")]);
+    }
+
+    #[test]
+    fn fee_rounding() {
+        // A 10% fee on 105pcoin is 10.5pcoin, which must be rounded one way or the other.
+        for (rounding, expected_fee) in [(RoundingMode::Down, 10), (RoundingMode::Up, 11)] {
+            // Create mocks
+            let mut deps = mock_dependencies(&[]);
+
+            // Create config state
+            instantiate(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("feebucket", &[]),
+                InitMsg {
+                    contract_name: "tutorial.sc.pb".into(),
+                    purchase_denoms: vec!["pcoin".into()],
+                    merchant_address: "merchant".into(),
+                    fee_percent: Decimal::percent(10),
+                    fee_rounding: Some(rounding),
+                    fee_splits: None,
+                },
+            )
+            .unwrap();
+
+            // Make a purchase that doesn't divide evenly
+            let res = execute(
+                deps.as_mut(),
+                mock_env(),
+                mock_info("consumer", &[coin(105, "pcoin")]),
+                ExecuteMsg::Purchase {
+                    id: "a7918172-ac09-43f6-bc4b-7ac2fbad17e9".into(),
+                },
+            )
+            .unwrap();
+
+            // Ensure the merchant and fee amounts add up to the purchase amount.
+            let amounts: Vec<u128> = res
+                .messages
+                .into_iter()
+                .map(|msg| match msg {
+                    CosmosMsg::Bank(BankMsg::Send { amount, .. }) => amount[0].amount.u128(),
+                    _ => panic!("unexpected message type"),
+                })
+                .collect();
+            assert_eq!(amounts, vec![105 - expected_fee, expected_fee]);
+            assert_eq!(amounts.iter().sum::<u128>(), 105);
+        }
+    }
+
+    #[test]
+    fn fee_splits() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create config state, with their own fee collectors for two of the three purchase denoms
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into(), "acoin".into(), "bcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: Some(vec![
+                    FeeSplitInit {
+                        denom: "pcoin".into(),
+                        collectors: vec![
+                            ("alice".into(), Decimal::percent(60)),
+                            ("bob".into(), Decimal::percent(40)),
+                        ],
+                    },
+                    FeeSplitInit {
+                        denom: "acoin".into(),
+                        collectors: vec![("carol".into(), Decimal::one())],
+                    },
+                ]),
+            },
+        )
+        .unwrap();
+
+        // Make a purchase with all three denoms
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info(
+                "consumer",
+                &[coin(100, "pcoin"), coin(50, "acoin"), coin(20, "bcoin")],
+            ),
+            ExecuteMsg::Purchase {
+                id: "a7918172-ac09-43f6-bc4b-7ac2fbad17e9".into(),
+            },
+        )
+        .unwrap();
+
+        // Ensure each fee was routed per the split table for its denom.
+        // 10% fees => 10pcoin split 6/4 between alice and bob, 5acoin for carol, and 2bcoin for the
+        // fee bucket, since bcoin has no split.
+        let send = |to_address: &str, amount: Vec<Coin>| {
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: to_address.into(),
+                amount,
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![
+                send("merchant", vec![coin(90, "pcoin")]),
+                send("alice", vec![coin(6, "pcoin")]),
+                send("bob", vec![coin(4, "pcoin")]),
+                send("merchant", vec![coin(45, "acoin")]),
+                send("carol", vec![coin(5, "acoin")]),
+                send("merchant", vec![coin(18, "bcoin")]),
+                send("feebucket", vec![coin(2, "bcoin")]),
+            ]
+        );
+
+        // Ensure the fees were accumulated by denom
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::GetFeesCollected {}).unwrap();
+        let resp: FeesCollectedResponse = from_binary(&bin).unwrap();
+        assert_eq!(
+            resp.fees,
+            vec![coin(10, "pcoin"), coin(5, "acoin"), coin(2, "bcoin")]
+        );
+    }
+
+    #[test]
+    fn invalid_fee_split_init() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create an init message with split weights that don't add up to one.
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: Some(vec![FeeSplitInit {
+                    denom: "pcoin".into(),
+                    collectors: vec![
+                        ("alice".into(), Decimal::percent(60)),
+                        ("bob".into(), Decimal::percent(60)),
+                    ],
+                }]),
+            },
+        )
+        .unwrap_err();
+
+        // Ensure the expected error was returned
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "fee split weights for pcoin must sum to 1")
+            }
+            _ => panic!("unexpected init error"),
+        }
+
+        // Create an init message with a split for a denom that can't be used for purchases.
+        let err = instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: Some(vec![FeeSplitInit {
+                    denom: "acoin".into(),
+                    collectors: vec![("alice".into(), Decimal::one())],
+                }]),
+            },
+        )
+        .unwrap_err();
+
+        // Ensure the expected error was returned
+        match err {
+            StdError::GenericErr { msg, .. } => {
+                assert_eq!(msg, "fee split denom is not a purchase denom: acoin")
+            }
+            _ => panic!("unexpected init error"),
+        }
+    }
+
+    #[test]
+    fn update_merchant() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create config state
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
+            },
+        )
+        .unwrap(); // Panics on error
+
+        // Ensure only the fee collection address can update the merchant
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("merchant", &[]),
+            ExecuteMsg::UpdateMerchant {
+                merchant_address: "new-merchant".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Unauthorized {} => {}
+            _ => panic!("unexpected execute error"),
+        }
+
+        // Ensure the merchant can't be the fee collection address
+        let err = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            ExecuteMsg::UpdateMerchant {
+                merchant_address: "feebucket".into(),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "merchant address can't be the fee collection address")
+            }
+            _ => panic!("unexpected execute error"),
+        }
+
+        // Update the merchant
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            ExecuteMsg::UpdateMerchant {
+                merchant_address: "new-merchant".into(),
+            },
+        )
+        .unwrap(); // Panics on error
+
+        // Ensure the new merchant address was stored.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::QueryRequest {}).unwrap();
+        let resp: QueryResponse = from_binary(&bin).unwrap();
+        assert_eq!(resp.merchant_address, Addr::unchecked("new-merchant"));
+    }
+
+    #[test]
+    fn valid_legacy_migrate() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Seed config state persisted by the original contract, with a single purchase denom
+        let key = cosmwasm_storage::to_length_prefixed(crate::state::CONFIG_KEY);
+        deps.storage.set(
+            &key,
+            br#"{"purchase_denom":"pcoin","merchant_address":"merchant","fee_collection_address":"feebucket","fee_percent":"0.1"}"#,
+        );
+
+        // Migrate to a 20% fee
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                new_fee_percent: Decimal::percent(20),
+            },
+        )
+        .unwrap(); // Panics on error
+
+        // Ensure the config was upgraded, keeping the original purchase denom.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::QueryRequest {}).unwrap();
+        let resp: QueryResponse = from_binary(&bin).unwrap();
+        assert_eq!(resp.purchase_denoms, vec!["pcoin"]);
+        assert_eq!(resp.merchant_address, Addr::unchecked("merchant"));
+        assert_eq!(resp.fee_collection_address, Addr::unchecked("feebucket"));
+        assert_eq!(resp.fee_percent, Decimal::percent(20));
+        assert!(resp.fees_collected.is_empty());
+    }
+
+    #[test]
+    fn valid_migrate() {
+        // Create mocks
+        let mut deps = mock_dependencies(&[]);
+
+        // Create config state
+        instantiate(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("feebucket", &[]),
+            InitMsg {
+                contract_name: "tutorial.sc.pb".into(),
+                purchase_denoms: vec!["pcoin".into()],
+                merchant_address: "merchant".into(),
+                fee_percent: Decimal::percent(10),
+                fee_rounding: None,
+                fee_splits: None,
+            },
+        )
+        .unwrap(); // Panics on error
+
+        // Ensure fees outside the init range are rejected.
+        let err = migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                new_fee_percent: Decimal::percent(30),
+            },
+        )
+        .unwrap_err();
+        match err {
+            ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "fee percent must be > 0.0 and <= 0.25")
+            }
+            _ => panic!("unexpected migrate error"),
+        }
+
+        // Migrate to a 20% fee
+        migrate(
+            deps.as_mut(),
+            mock_env(),
+            MigrateMsg {
+                new_fee_percent: Decimal::percent(20),
+            },
+        )
+        .unwrap(); // Panics on error
+
+        // Ensure the new fee percentage was stored.
+        let bin = query(deps.as_ref(), mock_env(), QueryMsg::QueryRequest {}).unwrap();
+        let resp: QueryResponse = from_binary(&bin).unwrap();
+        assert_eq!(resp.fee_percent, Decimal::percent(20));
+    }
 }
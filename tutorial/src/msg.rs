@@ -1,16 +1,25 @@
-use cosmwasm_std::Decimal;
+use cosmwasm_std::{Coin, Decimal};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::State;
+use crate::state::{RoundingMode, State};
 
 /// A message sent to initialize the contract state.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub contract_name: String,
-    pub purchase_denom: String,
+    pub purchase_denoms: Vec<String>,
     pub merchant_address: String,
     pub fee_percent: Decimal,
+    pub fee_rounding: Option<RoundingMode>,
+    pub fee_splits: Option<Vec<FeeSplitInit>>,
+}
+
+/// A purchase denom, with the weight of its fees each collector receives.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeSplitInit {
+    pub denom: String,
+    pub collectors: Vec<(String, Decimal)>,
 }
 
 /// A message sent to transfer funds and collect fees for a purchase.
@@ -18,6 +27,7 @@ pub struct InitMsg {
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     Purchase { id: String },
+    UpdateMerchant { merchant_address: String },
 }
 
 /// A message sent to query contract config state.
@@ -25,7 +35,21 @@ pub enum ExecuteMsg {
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     QueryRequest {},
+    GetFeesCollected {},
 }
 
 /// A type alias for contract state.
 pub type QueryResponse = State;
+
+/// Migrate the contract, setting a new fee percentage.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrateMsg {
+    pub new_fee_percent: Decimal,
+}
+
+/// The cumulative fees collected by the contract, by denom.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeesCollectedResponse {
+    pub fees: Vec<Coin>,
+}
@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Addr, Decimal, Storage};
+use cosmwasm_std::{Addr, Coin, Decimal, Storage};
 use cosmwasm_storage::{singleton, singleton_read, ReadonlySingleton, Singleton};
 
 pub static CONFIG_KEY: &[u8] = b"config";
@@ -9,20 +9,81 @@ pub static CONFIG_KEY: &[u8] = b"config";
 /// Fields that comprise the smart contract state
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
-    // The required purchase denomination
-    pub purchase_denom: String,
+    // The accepted purchase denominations
+    pub purchase_denoms: Vec<String>,
     // The merchant account
     pub merchant_address: Addr,
     // The fee collection account
     pub fee_collection_address: Addr,
     // The percentage to collect on transfers
     pub fee_percent: Decimal,
+    // The cumulative fees collected, merged by denom
+    #[serde(default)]
+    pub fees_collected: Vec<Coin>,
+    // Which way to round fees; the merchant gets the rest of the purchase amount
+    #[serde(default)]
+    pub fee_rounding: RoundingMode,
+    // Fee collectors for purchase denoms whose fees don't all go to the fee collection account
+    #[serde(default)]
+    pub fee_splits: Vec<FeeSplit>,
+}
+
+/// Fields persisted before multiple purchase denoms were accepted; used by migrations.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LegacyState {
+    pub purchase_denom: String,
+    pub merchant_address: Addr,
+    pub fee_collection_address: Addr,
+    pub fee_percent: Decimal,
+    #[serde(default)]
+    pub fees_collected: Vec<Coin>,
+    #[serde(default)]
+    pub fee_rounding: RoundingMode,
+    #[serde(default)]
+    pub fee_splits: Vec<FeeSplit>,
+}
+
+impl LegacyState {
+    pub fn upgrade(self) -> State {
+        State {
+            purchase_denoms: vec![self.purchase_denom],
+            merchant_address: self.merchant_address,
+            fee_collection_address: self.fee_collection_address,
+            fee_percent: self.fee_percent,
+            fees_collected: self.fees_collected,
+            fee_rounding: self.fee_rounding,
+            fee_splits: self.fee_splits,
+        }
+    }
 }
 
-pub fn config(storage: &mut dyn Storage) -> Singleton<State> {
+/// A purchase denom whose fees are shared by a set of collectors, weighted by the fraction each
+/// receives. The weights sum to one.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeSplit {
+    pub denom: String,
+    pub collectors: Vec<(Addr, Decimal)>,
+}
+
+/// The direction fees are rounded when they aren't a whole amount.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    // Round fees down, giving the remainder to the merchant
+    #[default]
+    Down,
+    // Round fees up, giving the remainder to the fee collector
+    Up,
+}
+
+pub fn config(storage: &mut dyn Storage) -> Singleton<'_, State> {
     singleton(storage, CONFIG_KEY)
 }
 
-pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<State> {
+pub fn config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, State> {
+    singleton_read(storage, CONFIG_KEY)
+}
+
+pub fn legacy_config_read(storage: &dyn Storage) -> ReadonlySingleton<'_, LegacyState> {
     singleton_read(storage, CONFIG_KEY)
 }